@@ -0,0 +1,4 @@
+-- Your SQL goes here
+
+ALTER TABLE gotd_schedules
+  ADD COLUMN timezone VARCHAR(64) NOT NULL DEFAULT 'America/New_York';
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE gotd_schedules DROP COLUMN last_sent_ts;
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+
+ALTER TABLE gotd_schedules
+  ADD COLUMN last_sent_ts TIMESTAMP NULL DEFAULT NULL;
@@ -0,0 +1,15 @@
+-- Your SQL goes here
+
+CREATE TABLE gotd_history(
+  id INT NOT NULL AUTO_INCREMENT,
+  channel_id BIGINT UNSIGNED NOT NULL,
+  game_id INT NOT NULL,
+  game_guid VARCHAR(64) NOT NULL,
+  game_name VARCHAR(255) NOT NULL,
+  sent_on_ts TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (id)
+);
+
+CREATE INDEX history_channel_id_index ON gotd_history(channel_id);
+
+CREATE INDEX history_channel_game_id_index ON gotd_history(channel_id, game_id);
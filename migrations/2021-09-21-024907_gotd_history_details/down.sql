@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE gotd_history
+  DROP COLUMN site_detail_url,
+  DROP COLUMN platforms;
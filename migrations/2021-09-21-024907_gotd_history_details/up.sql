@@ -0,0 +1,5 @@
+-- Your SQL goes here
+
+ALTER TABLE gotd_history
+  ADD COLUMN site_detail_url VARCHAR(512) NULL,
+  ADD COLUMN platforms VARCHAR(1024) NULL;
@@ -0,0 +1,18 @@
+-- Your SQL goes here
+
+CREATE TABLE wyr_votes(
+  id INT NOT NULL AUTO_INCREMENT,
+  wyr_id VARCHAR(64) NOT NULL,
+  message_id BIGINT UNSIGNED NOT NULL,
+  channel_id BIGINT UNSIGNED NOT NULL,
+  guild_id BIGINT UNSIGNED NOT NULL,
+  user_id BIGINT UNSIGNED NOT NULL,
+  option_idx INT NOT NULL,
+  voted_on_ts TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (id),
+  UNIQUE KEY wyr_vote_unique_index (wyr_id, message_id, user_id)
+);
+
+CREATE INDEX wyr_votes_message_id_index ON wyr_votes(message_id);
+
+CREATE INDEX wyr_votes_guild_id_index ON wyr_votes(guild_id);
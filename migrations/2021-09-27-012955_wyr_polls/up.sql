@@ -0,0 +1,17 @@
+-- Your SQL goes here
+
+CREATE TABLE wyr_polls(
+  id INT NOT NULL AUTO_INCREMENT,
+  wyr_id VARCHAR(64) NOT NULL,
+  message_id BIGINT UNSIGNED NOT NULL,
+  channel_id BIGINT UNSIGNED NOT NULL,
+  guild_id BIGINT UNSIGNED NOT NULL,
+  created_on_ts TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+  closes_on_ts TIMESTAMP NULL DEFAULT NULL,
+  is_closed BOOLEAN NOT NULL DEFAULT false,
+  PRIMARY KEY (id)
+);
+
+CREATE INDEX wyr_polls_message_id_index ON wyr_polls(message_id);
+
+CREATE INDEX wyr_polls_open_index ON wyr_polls(is_closed, closes_on_ts);
@@ -0,0 +1,17 @@
+-- Your SQL goes here
+
+CREATE TABLE wyr_submissions(
+  id INT NOT NULL AUTO_INCREMENT,
+  guild_id BIGINT UNSIGNED NOT NULL,
+  channel_id BIGINT UNSIGNED NOT NULL,
+  submitted_by_id BIGINT UNSIGNED NOT NULL,
+  title VARCHAR(255) NOT NULL,
+  options TEXT NOT NULL,
+  status VARCHAR(16) NOT NULL DEFAULT 'pending',
+  reviewed_by_id BIGINT UNSIGNED NULL DEFAULT NULL,
+  created_on_ts TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+  reviewed_on_ts TIMESTAMP NULL DEFAULT NULL,
+  PRIMARY KEY (id)
+);
+
+CREATE INDEX wyr_submissions_guild_status_index ON wyr_submissions(guild_id, status);
@@ -0,0 +1,10 @@
+-- Your SQL goes here
+
+CREATE TABLE wyr_shown(
+  id INT NOT NULL AUTO_INCREMENT,
+  guild_id BIGINT UNSIGNED NOT NULL,
+  wyr_id VARCHAR(64) NOT NULL,
+  shown_on_ts TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (id),
+  UNIQUE KEY wyr_shown_guild_wyr_index (guild_id, wyr_id)
+);
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+
+ALTER TABLE gotd_schedules
+  ADD COLUMN kind VARCHAR(16) NOT NULL DEFAULT 'gotd';
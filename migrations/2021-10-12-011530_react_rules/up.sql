@@ -0,0 +1,14 @@
+-- Your SQL goes here
+
+CREATE TABLE react_rules(
+  id INT NOT NULL AUTO_INCREMENT,
+  guild_id BIGINT UNSIGNED NOT NULL,
+  trigger_kind VARCHAR(16) NOT NULL,
+  trigger_value VARCHAR(255) NOT NULL,
+  reactions VARCHAR(255) NOT NULL,
+  created_by_id BIGINT UNSIGNED NOT NULL,
+  created_on_ts TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (id)
+);
+
+CREATE INDEX react_rules_guild_index ON react_rules(guild_id);
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+
+CREATE TABLE game_cache(
+  game_id INT NOT NULL,
+  game_json TEXT NOT NULL,
+  cached_on_ts TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (game_id)
+);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE gotd_schedules
+  DROP COLUMN game_platform,
+  DROP COLUMN game_decade;
@@ -0,0 +1,5 @@
+-- Your SQL goes here
+
+ALTER TABLE gotd_schedules
+  ADD COLUMN game_platform VARCHAR(32),
+  ADD COLUMN game_decade INT;
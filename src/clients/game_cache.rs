@@ -0,0 +1,273 @@
+use super::game_filter::GameFilter;
+use super::game_source::GameSource;
+use super::gotd::Game;
+use crate::store::model::{CachedGame, NewCachedGame};
+use crate::store::storage::GameCacheDb;
+use rand::Rng;
+use serenity::async_trait;
+use std::error::Error;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+// how many cached games to look through for one matching a filter
+const FALLBACK_PICKS: usize = 10;
+
+/**
+ * Keeps every game that comes back from another source in the db, so
+ * there's still something to post when that source is down and old games
+ * can be shown again without asking for them.
+ */
+pub struct CachedGameSource {
+  inner: Arc<dyn GameSource>,
+  db: Arc<dyn GameCacheDb + Send + Sync>,
+}
+
+impl CachedGameSource {
+  pub fn new(inner: Arc<dyn GameSource>, db: Arc<dyn GameCacheDb + Send + Sync>) -> Self {
+    Self { inner, db }
+  }
+
+  /**
+   * Fetch random games into the cache, one every `every`, until it holds
+   * `target` of them. Failures are only logged, the next tick tries again.
+   */
+  pub async fn prewarm(&self, target: i64, every: Duration) {
+    info!("Warming the game cache up to {} games", target);
+    loop {
+      match self.db.count_cached_games() {
+        Ok(count) if count >= target => {
+          info!("Game cache holds {} games, done warming", count);
+          return;
+        }
+        Ok(_) => {
+          if let Err(why) = self.random_game(&GameFilter::default()).await {
+            warn!("Failed to fetch a game to warm the cache: {}", why);
+          }
+        }
+        Err(why) => warn!("Failed to count cached games: {}", why),
+      }
+
+      tokio::time::sleep(every).await;
+    }
+  }
+
+  fn put(&self, game: &Game) {
+    let saved = serde_json::to_string(game)
+      .map_err(|why| Box::new(why) as Box<dyn Error + Send + Sync>)
+      .and_then(|game_json| {
+        self.db.cache_game(NewCachedGame {
+          game_id: game.id,
+          game_json,
+        })
+      });
+    if let Err(why) = saved {
+      warn!("Failed to cache game {}: {}", game.id, why);
+    }
+  }
+
+  /**
+   * Look at a few random cached games for one that matches
+   */
+  fn random_cached(
+    &self,
+    filter: &GameFilter,
+  ) -> Result<Option<Game>, Box<dyn Error + Send + Sync>> {
+    let count = self.db.count_cached_games()?;
+    if count <= 0 {
+      return Ok(None);
+    }
+
+    for _ in 0..FALLBACK_PICKS {
+      let offset = rand::thread_rng().gen_range(0..count);
+      if let Some(game) = self.db.get_cached_game_at(offset)?.and_then(decode) {
+        if filter.matches(&game) {
+          return Ok(Some(game));
+        }
+      }
+    }
+    Ok(None)
+  }
+}
+
+/**
+ * A game that won't decode any more is treated like it was never cached,
+ * it gets replaced the next time it's fetched
+ */
+fn decode(cached: CachedGame) -> Option<Game> {
+  match serde_json::from_str::<Game>(&cached.game_json) {
+    Ok(game) => Some(game),
+    Err(why) => {
+      warn!("Cached game {} doesn't decode: {}", cached.game_id, why);
+      None
+    }
+  }
+}
+
+#[async_trait]
+impl GameSource for CachedGameSource {
+  async fn random_game(&self, filter: &GameFilter) -> Result<Game, Box<dyn Error + Send + Sync>> {
+    let game = self.inner.random_game(filter).await?;
+    self.put(&game);
+    Ok(game)
+  }
+
+  async fn game_by_id(&self, id: i32) -> Result<Option<Game>, Box<dyn Error + Send + Sync>> {
+    match self.db.get_cached_game(id) {
+      Ok(Some(cached)) => {
+        if let Some(game) = decode(cached) {
+          return Ok(Some(game));
+        }
+      }
+      Ok(None) => {}
+      Err(why) => warn!("Failed to look up cached game {}: {}", id, why),
+    }
+
+    let game = self.inner.game_by_id(id).await?;
+    if let Some(game) = &game {
+      self.put(game);
+    }
+    Ok(game)
+  }
+
+  async fn fallback_game(&self, filter: &GameFilter) -> Option<Game> {
+    match self.random_cached(filter) {
+      Ok(game) => game,
+      Err(why) => {
+        warn!("Failed to pick a cached game: {}", why);
+        None
+      }
+    }
+  }
+
+  fn can_filter(&self) -> bool {
+    self.inner.can_filter()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::sync::Mutex;
+
+  /**
+   * The cache table, kept in a vec sorted by id
+   */
+  #[derive(Default)]
+  struct MemoryCache {
+    games: Mutex<Vec<CachedGame>>,
+  }
+
+  impl GameCacheDb for MemoryCache {
+    fn cache_game(&self, game: NewCachedGame) -> Result<(), Box<dyn Error + Send + Sync>> {
+      let mut games = self.games.lock().unwrap();
+      games.retain(|g| g.game_id != game.game_id);
+      games.push(CachedGame {
+        game_id: game.game_id,
+        game_json: game.game_json,
+        cached_on_ts: chrono::Utc::now().naive_utc(),
+      });
+      games.sort_by_key(|g| g.game_id);
+      Ok(())
+    }
+
+    fn get_cached_game(
+      &self,
+      game_id: i32,
+    ) -> Result<Option<CachedGame>, Box<dyn Error + Send + Sync>> {
+      let games = self.games.lock().unwrap();
+      Ok(games.iter().find(|g| g.game_id == game_id).map(copy))
+    }
+
+    fn get_cached_game_at(
+      &self,
+      offset: i64,
+    ) -> Result<Option<CachedGame>, Box<dyn Error + Send + Sync>> {
+      let games = self.games.lock().unwrap();
+      Ok(games.get(offset as usize).map(copy))
+    }
+
+    fn count_cached_games(&self) -> Result<i64, Box<dyn Error + Send + Sync>> {
+      Ok(self.games.lock().unwrap().len() as i64)
+    }
+  }
+
+  fn copy(game: &CachedGame) -> CachedGame {
+    CachedGame {
+      game_id: game.game_id,
+      game_json: game.game_json.clone(),
+      cached_on_ts: game.cached_on_ts,
+    }
+  }
+
+  /**
+   * Hands out its games in order, then fails like GiantBomb being down
+   */
+  struct Flaky {
+    games: Mutex<Vec<Game>>,
+  }
+
+  #[async_trait]
+  impl GameSource for Flaky {
+    async fn random_game(
+      &self,
+      _filter: &GameFilter,
+    ) -> Result<Game, Box<dyn Error + Send + Sync>> {
+      let mut games = self.games.lock().unwrap();
+      if games.is_empty() {
+        return Err("GiantBomb is down".into());
+      }
+      Ok(games.remove(0))
+    }
+
+    async fn game_by_id(&self, _id: i32) -> Result<Option<Game>, Box<dyn Error + Send + Sync>> {
+      Err("GiantBomb is down".into())
+    }
+  }
+
+  fn game(id: i32, name: &str) -> Game {
+    let mut game = Game::default();
+    game.id = id;
+    game.name = String::from(name);
+    game
+  }
+
+  fn source(games: Vec<Game>) -> CachedGameSource {
+    CachedGameSource::new(
+      Arc::new(Flaky {
+        games: Mutex::new(games),
+      }),
+      Arc::new(MemoryCache::default()),
+    )
+  }
+
+  #[tokio::test]
+  async fn fetched_games_can_be_found_again() {
+    let any = GameFilter::default();
+    let source = source(vec![game(16205, "Doom")]);
+    assert!(source.fallback_game(&any).await.is_none());
+
+    source.random_game(&any).await.unwrap();
+    assert!(source.random_game(&any).await.is_err());
+
+    let fallback = source.fallback_game(&any).await.unwrap();
+    assert_eq!(fallback.name, "Doom");
+    let snes = GameFilter::parse(Some("snes"), None).unwrap();
+    assert!(source.fallback_game(&snes).await.is_none());
+    let by_id = source.game_by_id(16205).await.unwrap().unwrap();
+    assert_eq!(by_id.name, "Doom");
+    assert!(source.game_by_id(1).await.is_err());
+  }
+
+  #[tokio::test]
+  async fn prewarm_stops_at_the_target() {
+    let source = source(vec![
+      game(1, "Quake"),
+      game(2, "Quake II"),
+      game(3, "Quake III Arena"),
+    ]);
+
+    source.prewarm(2, Duration::from_millis(1)).await;
+    assert_eq!(source.db.count_cached_games().unwrap(), 2);
+  }
+}
@@ -0,0 +1,327 @@
+use super::gotd::Game;
+use std::fmt;
+
+/**
+ * A platform random games can be narrowed to. The id is GiantBomb's, the
+ * key is what gets stored on a schedule.
+ */
+#[derive(Debug, PartialEq)]
+pub struct Platform {
+  pub key: &'static str,
+  pub id: i32,
+  pub name: &'static str,
+}
+
+// discord only shows 25 choices for an option
+pub const PLATFORMS: &[Platform] = &[
+  Platform {
+    key: "nes",
+    id: 21,
+    name: "Nintendo Entertainment System",
+  },
+  Platform {
+    key: "snes",
+    id: 9,
+    name: "Super Nintendo Entertainment System",
+  },
+  Platform {
+    key: "n64",
+    id: 43,
+    name: "Nintendo 64",
+  },
+  Platform {
+    key: "gamecube",
+    id: 23,
+    name: "GameCube",
+  },
+  Platform {
+    key: "wii",
+    id: 36,
+    name: "Wii",
+  },
+  Platform {
+    key: "wii-u",
+    id: 139,
+    name: "Wii U",
+  },
+  Platform {
+    key: "switch",
+    id: 157,
+    name: "Nintendo Switch",
+  },
+  Platform {
+    key: "gb",
+    id: 3,
+    name: "Game Boy",
+  },
+  Platform {
+    key: "gba",
+    id: 4,
+    name: "Game Boy Advance",
+  },
+  Platform {
+    key: "ds",
+    id: 52,
+    name: "Nintendo DS",
+  },
+  Platform {
+    key: "3ds",
+    id: 117,
+    name: "Nintendo 3DS",
+  },
+  Platform {
+    key: "genesis",
+    id: 6,
+    name: "Genesis",
+  },
+  Platform {
+    key: "saturn",
+    id: 42,
+    name: "Saturn",
+  },
+  Platform {
+    key: "dreamcast",
+    id: 37,
+    name: "Dreamcast",
+  },
+  Platform {
+    key: "ps1",
+    id: 22,
+    name: "PlayStation",
+  },
+  Platform {
+    key: "ps2",
+    id: 19,
+    name: "PlayStation 2",
+  },
+  Platform {
+    key: "ps3",
+    id: 35,
+    name: "PlayStation 3",
+  },
+  Platform {
+    key: "ps4",
+    id: 146,
+    name: "PlayStation 4",
+  },
+  Platform {
+    key: "ps5",
+    id: 176,
+    name: "PlayStation 5",
+  },
+  Platform {
+    key: "psp",
+    id: 18,
+    name: "PlayStation Portable",
+  },
+  Platform {
+    key: "xbox",
+    id: 32,
+    name: "Xbox",
+  },
+  Platform {
+    key: "xbox-360",
+    id: 20,
+    name: "Xbox 360",
+  },
+  Platform {
+    key: "xbox-one",
+    id: 145,
+    name: "Xbox One",
+  },
+  Platform {
+    key: "pc",
+    id: 94,
+    name: "PC",
+  },
+  Platform {
+    key: "arcade",
+    id: 84,
+    name: "Arcade",
+  },
+];
+
+/**
+ * First year of every decade that can be picked
+ */
+pub const DECADES: &[i32] = &[1980, 1990, 2000, 2010, 2020];
+
+/**
+ * What a random game has to match. The default matches every game.
+ */
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GameFilter {
+  pub platform: Option<&'static Platform>,
+  pub decade: Option<i32>,
+}
+
+impl GameFilter {
+  /**
+   * Check each option against the known choices. Schedules store the
+   * same values, so they come back through here too.
+   */
+  pub fn parse(platform: Option<&str>, decade: Option<i64>) -> Result<Self, String> {
+    let platform = match platform {
+      Some(key) => Some(
+        PLATFORMS
+          .iter()
+          .find(|p| p.key.eq_ignore_ascii_case(key.trim()))
+          .ok_or_else(|| format!("`{}` isn't a platform I can filter on", key))?,
+      ),
+      None => None,
+    };
+    let decade = match decade {
+      Some(d) => Some(
+        DECADES
+          .iter()
+          .find(|start| **start as i64 == d)
+          .copied()
+          .ok_or_else(|| format!("`{}` isn't a decade I can filter on", d))?,
+      ),
+      None => None,
+    };
+    Ok(Self { platform, decade })
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.platform.is_none() && self.decade.is_none()
+  }
+
+  /**
+   * The `filter` param for the GiantBomb /games list
+   */
+  pub fn api_filter(&self) -> Option<String> {
+    let mut parts = Vec::<String>::new();
+    if let Some(p) = self.platform {
+      parts.push(format!("platforms:{}", p.id));
+    }
+    if let Some(d) = self.decade {
+      parts.push(format!(
+        "original_release_date:{}-01-01 00:00:00|{}-12-31 23:59:59",
+        d,
+        d + 9
+      ));
+    }
+
+    match parts.is_empty() {
+      true => None,
+      false => Some(parts.join(",")),
+    }
+  }
+
+  /**
+   * Whether the game matches. Anything filtered on that the game doesn't
+   * list doesn't match.
+   */
+  pub fn matches(&self, game: &Game) -> bool {
+    let platform = match self.platform {
+      Some(p) => game
+        .platforms
+        .as_ref()
+        .is_some_and(|ps| ps.iter().any(|gp| gp.id == p.id)),
+      None => true,
+    };
+    let decade = match self.decade {
+      Some(d) => release_year(game).is_some_and(|y| y >= d && y < d + 10),
+      None => true,
+    };
+    platform && decade
+  }
+}
+
+/**
+ * Lists what's filtered on, like `Nintendo 64, 1990s`
+ */
+impl fmt::Display for GameFilter {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let mut parts = Vec::<String>::new();
+    if let Some(p) = self.platform {
+      parts.push(String::from(p.name));
+    }
+    if let Some(d) = self.decade {
+      parts.push(format!("{}s", d));
+    }
+
+    match parts.is_empty() {
+      true => write!(f, "any game"),
+      false => write!(f, "{}", parts.join(", ")),
+    }
+  }
+}
+
+/**
+ * The year a game came out, or is expected to
+ */
+fn release_year(game: &Game) -> Option<i32> {
+  game
+    .original_release_date
+    .as_ref()
+    .and_then(|d| d.get(..4))
+    .and_then(|y| y.parse::<i32>().ok())
+    .or(game.expected_release_year)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn game(json: &str) -> Game {
+    serde_json::from_str::<Game>(json).unwrap()
+  }
+
+  #[test]
+  fn parse_only_takes_known_choices() {
+    let filter = GameFilter::parse(Some("SNES"), Some(1990)).unwrap();
+    assert_eq!(filter.platform.unwrap().id, 9);
+    assert_eq!(filter.decade, Some(1990));
+    assert_eq!(
+      filter.to_string(),
+      "Super Nintendo Entertainment System, 1990s"
+    );
+
+    assert!(GameFilter::parse(None, None).unwrap().is_empty());
+    assert!(GameFilter::parse(Some("virtual-boy"), None).is_err());
+    assert!(GameFilter::parse(None, Some(1995)).is_err());
+  }
+
+  #[test]
+  fn api_filter_joins_every_filter() {
+    assert_eq!(GameFilter::default().api_filter(), None);
+    assert_eq!(
+      GameFilter::parse(Some("n64"), Some(1990))
+        .unwrap()
+        .api_filter(),
+      Some(String::from(
+        "platforms:43,original_release_date:1990-01-01 00:00:00|1999-12-31 23:59:59"
+      ))
+    );
+  }
+
+  #[test]
+  fn matches_checks_every_filter() {
+    let doom = game(
+      r#"{"id": 16205, "guid": "3030-16205", "name": "Doom", "original_release_date": "1993-12-10",
+        "platforms": [{"api_detail_url": "", "id": 94, "name": "PC", "site_detail_url": ""}],
+        "themes": [{"api_detail_url": "", "id": 1, "name": "Sci-Fi", "site_detail_url": ""}]}"#,
+    );
+    let upcoming = game(
+      r#"{"id": 1, "guid": "3030-1", "name": "Soon", "expected_release_year": 2024, "platforms": null}"#,
+    );
+
+    assert!(GameFilter::default().matches(&doom));
+    assert!(GameFilter::parse(Some("pc"), Some(1990))
+      .unwrap()
+      .matches(&doom));
+    assert!(!GameFilter::parse(Some("snes"), None)
+      .unwrap()
+      .matches(&doom));
+    assert!(!GameFilter::parse(None, Some(1980)).unwrap().matches(&doom));
+
+    assert!(GameFilter::parse(None, Some(2020))
+      .unwrap()
+      .matches(&upcoming));
+    assert!(!GameFilter::parse(Some("pc"), None)
+      .unwrap()
+      .matches(&upcoming));
+  }
+}
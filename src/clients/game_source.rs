@@ -0,0 +1,168 @@
+use super::game_filter::GameFilter;
+use super::gotd::{self, Game};
+use crate::config::Config;
+use rand::seq::SliceRandom;
+use serenity::async_trait;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use tracing::info;
+
+/**
+ * Wherever games come from. Commands only talk to this, so they can run
+ * against fixtures instead of the network.
+ */
+#[async_trait]
+pub trait GameSource: Send + Sync {
+  /**
+   * Any game matching the filter
+   */
+  async fn random_game(&self, filter: &GameFilter) -> Result<Game, Box<dyn Error + Send + Sync>>;
+
+  /**
+   * The game with the GiantBomb id, if there is one
+   */
+  async fn game_by_id(&self, id: i32) -> Result<Option<Game>, Box<dyn Error + Send + Sync>>;
+
+  /**
+   * A game matching the filter to post anyway when `random_game` fails, if
+   * there's one to be had
+   */
+  async fn fallback_game(&self, _filter: &GameFilter) -> Option<Game> {
+    None
+  }
+
+  /**
+   * Whether `random_game` can narrow games down by a filter at all
+   */
+  fn can_filter(&self) -> bool {
+    true
+  }
+}
+
+/**
+ * Games from GiantBomb, or the proxy when there's no api key
+ */
+pub struct HttpGameSource {
+  config: Arc<Config>,
+}
+
+impl HttpGameSource {
+  pub fn new(config: Arc<Config>) -> Self {
+    Self { config }
+  }
+}
+
+#[async_trait]
+impl GameSource for HttpGameSource {
+  async fn random_game(&self, filter: &GameFilter) -> Result<Game, Box<dyn Error + Send + Sync>> {
+    Ok(gotd::get_random_game(&self.config, filter).await?)
+  }
+
+  async fn game_by_id(&self, id: i32) -> Result<Option<Game>, Box<dyn Error + Send + Sync>> {
+    Ok(gotd::get_game(&self.config, id).await?)
+  }
+
+  // the proxy only hands out any old game
+  fn can_filter(&self) -> bool {
+    self.config.giantbomb_api_key.is_some()
+  }
+}
+
+/**
+ * Games read once from the json files in a directory. Each file holds a
+ * single game or a list of them, in the shape GiantBomb returns.
+ */
+pub struct FixtureGameSource {
+  games: Vec<Game>,
+}
+
+impl FixtureGameSource {
+  pub fn load(dir: &Path) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    let mut games = Vec::<Game>::new();
+    for entry in fs::read_dir(dir)? {
+      let path = entry?.path();
+      if path.extension().and_then(|e| e.to_str()) != Some("json") {
+        continue;
+      }
+
+      let contents = fs::read_to_string(&path)?;
+      match serde_json::from_str::<Vec<Game>>(&contents) {
+        Ok(list) => games.extend(list),
+        Err(_) => games.push(
+          serde_json::from_str::<Game>(&contents)
+            .map_err(|why| format!("{} isn't a game: {}", path.display(), why))?,
+        ),
+      }
+    }
+
+    if games.is_empty() {
+      return Err(format!("No game fixtures in {}", dir.display()).into());
+    }
+    info!(
+      "Loaded {} game fixture(s) from {}",
+      games.len(),
+      dir.display()
+    );
+    Ok(Self { games })
+  }
+}
+
+#[async_trait]
+impl GameSource for FixtureGameSource {
+  async fn random_game(&self, filter: &GameFilter) -> Result<Game, Box<dyn Error + Send + Sync>> {
+    let matching = self
+      .games
+      .iter()
+      .filter(|g| filter.matches(g))
+      .collect::<Vec<&Game>>();
+    let game = matching
+      .choose(&mut rand::thread_rng())
+      .map(|g| (*g).clone());
+    game.ok_or_else(|| format!("No game fixtures for {}", filter).into())
+  }
+
+  async fn game_by_id(&self, id: i32) -> Result<Option<Game>, Box<dyn Error + Send + Sync>> {
+    Ok(self.games.iter().find(|g| g.id == id).cloned())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn fixtures() -> FixtureGameSource {
+    FixtureGameSource::load(Path::new("fixtures/games")).unwrap()
+  }
+
+  #[tokio::test]
+  async fn fixtures_load_single_games_and_lists() {
+    let source = fixtures();
+    assert_eq!(source.games.len(), 3);
+
+    let game = source.random_game(&GameFilter::default()).await.unwrap();
+    assert!(source.games.iter().any(|g| g.id == game.id));
+  }
+
+  #[tokio::test]
+  async fn fixtures_are_filtered() {
+    let source = fixtures();
+    let filter = GameFilter::parse(Some("pc"), Some(1990)).unwrap();
+    for _ in 0..10 {
+      let game = source.random_game(&filter).await.unwrap();
+      assert!(game.id == 16205 || game.id == 2484, "{}", game.name);
+    }
+
+    let filter = GameFilter::parse(Some("snes"), None).unwrap();
+    assert!(source.random_game(&filter).await.is_err());
+  }
+
+  #[tokio::test]
+  async fn fixtures_find_by_id() {
+    let source = fixtures();
+    let doom = source.game_by_id(16205).await.unwrap().unwrap();
+    assert_eq!(doom.name, "Doom");
+    assert!(source.game_by_id(-1).await.unwrap().is_none());
+  }
+}
@@ -1,7 +1,12 @@
+use super::game_filter::GameFilter;
+use crate::config::Config;
 use chrono::prelude::*;
-use serde::Deserialize;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::warn;
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct GameImage {
   pub original_url: Option<String>,
   pub super_url: Option<String>,
@@ -14,19 +19,19 @@ pub struct GameImage {
   pub tiny_url: Option<String>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Characteristic {
   api_detail_url: String,
-  id: i32,
+  pub id: i32,
   pub name: String,
   site_detail_url: String,
   abbreviation: Option<String>,
 }
 
-#[derive(Deserialize, Debug, Default)]
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
 pub struct Game {
-  id: i32,
-  guid: String,
+  pub id: i32,
+  pub guid: String,
   pub image: Option<GameImage>,
   pub name: String,
   pub deck: Option<String>,
@@ -44,18 +49,66 @@ pub struct Game {
   themes: Option<Vec<Characteristic>>,
 }
 
+/**
+ * Everything that can go wrong fetching games
+ */
 #[derive(Debug)]
-struct GotdError {
-  message: String,
+pub enum GotdError {
+  // couldn't connect, timed out or the connection dropped
+  Transport(reqwest::Error),
+  // the server answered with something other than a 2xx
+  Status(u16),
+  // told to slow down, with how long to wait if the server said
+  RateLimited(Option<Duration>),
+  // the body wasn't what we expected
+  Decode(String),
+  // GiantBomb answered but with an error status code
+  Api { code: i32, message: String },
+  // the request worked but there was no game in it
+  Empty(String),
+  NoApiKey,
+}
+
+impl GotdError {
+  /**
+   * Whether trying the same request again could work
+   */
+  pub fn is_retryable(&self) -> bool {
+    match self {
+      GotdError::Transport(why) => !why.is_builder(),
+      GotdError::Status(code) => *code >= 500 || *code == 408,
+      GotdError::RateLimited(_) => true,
+      _ => false,
+    }
+  }
 }
 
 impl std::fmt::Display for GotdError {
   fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-    write!(f, "GotdClientError: {}", self.message)
+    match self {
+      GotdError::Transport(why) if why.is_timeout() => write!(f, "request timed out: {}", why),
+      GotdError::Transport(why) => write!(f, "request failed: {}", why),
+      GotdError::Status(code) => write!(f, "server answered HTTP {}", code),
+      GotdError::RateLimited(Some(after)) => {
+        write!(f, "rate limited, retry after {}s", after.as_secs())
+      }
+      GotdError::RateLimited(None) => write!(f, "rate limited"),
+      GotdError::Decode(why) => write!(f, "couldn't read the response: {}", why),
+      GotdError::Api { code, message } => write!(f, "GiantBomb error {}: {}", code, message),
+      GotdError::Empty(why) => write!(f, "{}", why),
+      GotdError::NoApiKey => write!(f, "looking up games needs a GIANTBOMB_API_KEY"),
+    }
   }
 }
 
-impl std::error::Error for GotdError {}
+impl std::error::Error for GotdError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      GotdError::Transport(why) => Some(why),
+      _ => None,
+    }
+  }
+}
 
 #[derive(Deserialize, Debug)]
 struct AlorgResponse {
@@ -63,23 +116,299 @@ struct AlorgResponse {
   game: Option<Game>,
 }
 
-pub async fn get_random_game() -> Result<Game, Box<dyn std::error::Error + Send + Sync>> {
-  let url = "https://andrewbot-rs-xokx9.ondigitalocean.app/gb/games/random";
+/**
+ * Every `Game` field the /games list endpoint can fill in. Asking for only
+ * these keeps each response small.
+ */
+pub const GAME_FIELDS: &str = "id,guid,name,deck,image,original_release_date,site_detail_url,\
+expected_release_day,expected_release_month,expected_release_year,expected_release_quarter,\
+platforms";
+
+// giantbomb turns away requests without a user agent
+const USER_AGENT: &str = concat!("andrew-bot-rs/", env!("CARGO_PKG_VERSION"));
+
+// the status code giantbomb sends instead of a 429
+const GIANTBOMB_RATE_LIMITED: i32 = 107;
+
+/**
+ * How hard to try a request before giving up. Waits double after each
+ * failure up to `max_delay`, and a `Retry-After` longer than that is given
+ * up on rather than waited out.
+ */
+#[derive(Debug, Clone)]
+pub struct Retry {
+  pub retries: u32,
+  pub base_delay: Duration,
+  pub max_delay: Duration,
+  // for each attempt, not the whole request
+  pub timeout: Duration,
+}
+
+impl Retry {
+  pub fn from_config(config: &Config) -> Self {
+    Self {
+      retries: config.giantbomb_retries,
+      base_delay: Duration::from_secs(1),
+      max_delay: Duration::from_secs(30),
+      timeout: Duration::from_secs(config.giantbomb_timeout_secs),
+    }
+  }
+
+  /**
+   * How long to wait after the attempt'th failure, or None to give up.
+   * Jitter is between 0 and 1 and spreads the wait over its upper half.
+   */
+  fn next_delay(&self, attempt: u32, err: &GotdError, jitter: f64) -> Option<Duration> {
+    if attempt > self.retries || !err.is_retryable() {
+      return None;
+    }
+
+    let doubled = self
+      .base_delay
+      .checked_mul(1u32.checked_shl(attempt - 1).unwrap_or(u32::MAX))
+      .unwrap_or(self.max_delay)
+      .min(self.max_delay);
+    let backoff = doubled.mul_f64(0.5 + jitter.clamp(0.0, 1.0) / 2.0);
+    match err {
+      GotdError::RateLimited(Some(after)) if *after > self.max_delay => None,
+      GotdError::RateLimited(Some(after)) => Some(backoff.max(*after)),
+      _ => Some(backoff),
+    }
+  }
+}
+
+/**
+ * The envelope every GiantBomb api response comes in. A status code of 1
+ * means OK, anything else comes with an error message.
+ */
+#[derive(Deserialize, Debug)]
+struct GiantBombResponse<T> {
+  error: String,
+  status_code: i32,
+  number_of_total_results: i64,
+  results: T,
+}
+
+/**
+ * Get a random game matching the filter. Talks to GiantBomb directly when
+ * there's an api key, otherwise goes through the `gotd_api_url` proxy,
+ * which can't filter.
+ */
+pub async fn get_random_game(config: &Config, filter: &GameFilter) -> Result<Game, GotdError> {
+  let retry = Retry::from_config(config);
+  match &config.giantbomb_api_key {
+    Some(key) => get_random_giantbomb_game(&retry, &config.giantbomb_base_url, key, filter).await,
+    None if filter.is_empty() => get_random_proxy_game(&retry, &config.gotd_api_url).await,
+    None => Err(GotdError::NoApiKey),
+  }
+}
+
+/**
+ * Look up a single game by its GiantBomb id. Needs an api key, the proxy
+ * can only hand out random games.
+ */
+pub async fn get_game(config: &Config, id: i32) -> Result<Option<Game>, GotdError> {
+  let key = config
+    .giantbomb_api_key
+    .as_deref()
+    .ok_or(GotdError::NoApiKey)?;
+  let retry = Retry::from_config(config);
+  let client = client(&retry)?;
+  let filter = format!("id:{}", id);
+  let games = giantbomb_get::<Vec<Game>>(
+    &client,
+    &retry,
+    &games_url(&config.giantbomb_base_url, key, 0, GAME_FIELDS),
+    &[("filter", filter.as_str())],
+  )
+  .await?
+  .results;
+
+  Ok(games.into_iter().next())
+}
+
+fn client(retry: &Retry) -> Result<reqwest::Client, GotdError> {
+  reqwest::Client::builder()
+    .user_agent(USER_AGENT)
+    .timeout(retry.timeout)
+    .build()
+    .map_err(GotdError::Transport)
+}
+
+async fn get_random_proxy_game(retry: &Retry, url: &str) -> Result<Game, GotdError> {
+  let client = client(retry)?;
+  let client = &client;
+  let parsed = with_retries(retry, url, move || fetch::<AlorgResponse>(client.get(url))).await?;
+  parsed.game.ok_or(GotdError::Empty(parsed.message))
+}
+
+/**
+ * Ask for the number of matching games first, then for the one game at a
+ * random offset into that list
+ */
+async fn get_random_giantbomb_game(
+  retry: &Retry,
+  base_url: &str,
+  api_key: &str,
+  filter: &GameFilter,
+) -> Result<Game, GotdError> {
+  let client = client(retry)?;
+  let api_filter = filter.api_filter();
+  let params = match &api_filter {
+    Some(f) => vec![("filter", f.as_str())],
+    None => vec![],
+  };
+
+  let count = giantbomb_get::<Vec<serde_json::Value>>(
+    &client,
+    retry,
+    &games_url(base_url, api_key, 0, "id"),
+    &params,
+  )
+  .await?
+  .number_of_total_results;
+  if count <= 0 {
+    return Err(GotdError::Empty(format!(
+      "GiantBomb has no games for {}",
+      filter
+    )));
+  }
+
+  let offset = rand::thread_rng().gen_range(0..count);
+  giantbomb_get::<Vec<Game>>(
+    &client,
+    retry,
+    &games_url(base_url, api_key, offset, GAME_FIELDS),
+    &params,
+  )
+  .await?
+  .results
+  .into_iter()
+  .next()
+  .ok_or_else(|| GotdError::Empty(format!("GiantBomb had no game at offset {}", offset)))
+}
+
+/**
+ * GET a GiantBomb api url, retrying as needed. Extra params are url encoded
+ * onto it, for anything a member typed.
+ */
+async fn giantbomb_get<T: serde::de::DeserializeOwned>(
+  client: &reqwest::Client,
+  retry: &Retry,
+  url: &str,
+  params: &[(&str, &str)],
+) -> Result<GiantBombResponse<T>, GotdError> {
+  // the api key is in the url, keep it out of the logs
+  let endpoint = url.split('?').next().unwrap_or(url);
+  with_retries(retry, endpoint, move || async move {
+    let parsed = fetch::<GiantBombResponse<T>>(client.get(url).query(params)).await?;
+    match parsed.status_code {
+      1 => Ok(parsed),
+      GIANTBOMB_RATE_LIMITED => Err(GotdError::RateLimited(None)),
+      code => Err(GotdError::Api {
+        code,
+        message: parsed.error,
+      }),
+    }
+  })
+  .await
+}
 
-  let mut res = reqwest::get(url).await;
-  if let Err(why) = res {
-    tracing::error!("Failed game query, trying again: {}", why);
-    res = reqwest::get(url).await;
+/**
+ * Send the request once and decode the json body
+ */
+async fn fetch<T: serde::de::DeserializeOwned>(
+  request: reqwest::RequestBuilder,
+) -> Result<T, GotdError> {
+  let res = request.send().await.map_err(transport)?;
+  let status = res.status();
+  if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+    let after = res
+      .headers()
+      .get(reqwest::header::RETRY_AFTER)
+      .and_then(|v| v.to_str().ok())
+      .and_then(|v| parse_retry_after(v, Utc::now()));
+    return Err(GotdError::RateLimited(after));
+  }
+  if !status.is_success() {
+    return Err(GotdError::Status(status.as_u16()));
   }
-  let parsed = res?.json::<AlorgResponse>().await?;
-  match parsed.game {
-    Some(game) => Ok(game),
-    None => Err(Box::new(GotdError {
-      message: parsed.message,
-    })),
+
+  let body = res.bytes().await.map_err(transport)?;
+  serde_json::from_slice::<T>(&body).map_err(|why| GotdError::Decode(why.to_string()))
+}
+
+/**
+ * reqwest puts the whole url in its errors, api key and all, and these end
+ * up in the logs and the ops channel
+ */
+fn transport(why: reqwest::Error) -> GotdError {
+  GotdError::Transport(why.without_url())
+}
+
+/**
+ * Keep calling until it works, it fails in a way retrying won't fix, or
+ * the retries run out
+ */
+async fn with_retries<T, F, Fut>(retry: &Retry, what: &str, call: F) -> Result<T, GotdError>
+where
+  F: Fn() -> Fut,
+  Fut: std::future::Future<Output = Result<T, GotdError>>,
+{
+  let mut attempt = 0;
+  loop {
+    let err = match call().await {
+      Ok(v) => return Ok(v),
+      Err(err) => err,
+    };
+
+    attempt += 1;
+    let delay = match retry.next_delay(attempt, &err, rand::thread_rng().gen::<f64>()) {
+      Some(delay) => delay,
+      None => return Err(err),
+    };
+    warn!(
+      "{} failed (attempt {} of {}), retrying in {}ms: {}",
+      what,
+      attempt,
+      retry.retries + 1,
+      delay.as_millis(),
+      err
+    );
+    tokio::time::sleep(delay).await;
   }
 }
 
+/**
+ * A Retry-After header is either a number of seconds or an http date
+ */
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+  let value = value.trim();
+  if let Ok(secs) = value.parse::<u64>() {
+    return Some(Duration::from_secs(secs));
+  }
+
+  let at = DateTime::parse_from_rfc2822(value).ok()?;
+  Some(
+    (at.with_timezone(&Utc) - now)
+      .to_std()
+      .unwrap_or(Duration::from_secs(0)),
+  )
+}
+
+/**
+ * One game from the /games list at offset, with only the fields asked for
+ */
+fn games_url(base_url: &str, api_key: &str, offset: i64, fields: &str) -> String {
+  format!(
+    "{}/games/?api_key={}&format=json&limit=1&offset={}&field_list={}",
+    base_url.trim_end_matches('/'),
+    api_key,
+    offset,
+    fields
+  )
+}
+
 pub fn parse_image(game: &Game) -> String {
   match &game.image {
     Some(image) => match image
@@ -196,4 +525,262 @@ mod tests {
     let game = Game::default();
     assert_eq!(parse_date(&game), "No date listed");
   }
+
+  #[test]
+  fn games_url_trims_the_base() {
+    assert_eq!(
+      games_url("http://localhost:8080/api/", "key", 42, "id"),
+      "http://localhost:8080/api/games/?api_key=key&format=json&limit=1&offset=42&field_list=id"
+    );
+  }
+
+  #[test]
+  fn game_fields_are_all_game_fields() {
+    let json = GAME_FIELDS
+      .split(',')
+      .map(|f| format!("\"{}\": null", f))
+      .collect::<Vec<String>>()
+      .join(", ")
+      .replace("\"id\": null", "\"id\": 1")
+      .replace("\"guid\": null", "\"guid\": \"3030-1\"")
+      .replace("\"name\": null", "\"name\": \"Doom\"");
+    let game = serde_json::from_str::<Game>(&format!("{{{}}}", json)).unwrap();
+    assert_eq!(game.name, "Doom");
+  }
+
+  const OK: &str = "200 OK";
+  const COUNT: &str =
+    r#"{"error": "OK", "status_code": 1, "number_of_total_results": 1, "results": [{"id": 7}]}"#;
+  const QUAKE: &str = r#"{"error": "OK", "status_code": 1, "number_of_total_results": 1,
+    "results": [{"id": 7, "guid": "3030-7", "name": "Quake", "platforms": null}]}"#;
+
+  fn quick_retry(retries: u32) -> Retry {
+    Retry {
+      retries,
+      base_delay: Duration::from_millis(1),
+      max_delay: Duration::from_millis(10),
+      timeout: Duration::from_secs(5),
+    }
+  }
+
+  /**
+   * Answer each request on a local port with the next canned status line
+   * (and any headers after it) and body
+   */
+  async fn mock_giantbomb(responses: Vec<(&'static str, &'static str)>) -> String {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+      for (status, body) in responses {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut buf = [0u8; 4096];
+        let _ = socket.read(&mut buf).await;
+        let res = format!(
+          "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+          status,
+          body.len(),
+          body
+        );
+        socket.write_all(res.as_bytes()).await.unwrap();
+      }
+    });
+    format!("http://{}/api", addr)
+  }
+
+  #[tokio::test]
+  async fn random_giantbomb_game_uses_the_base_url() {
+    let base = mock_giantbomb(vec![(OK, COUNT), (OK, QUAKE)]).await;
+
+    let game = get_random_giantbomb_game(&quick_retry(0), &base, "key", &GameFilter::default())
+      .await
+      .unwrap();
+    assert_eq!(game.id, 7);
+    assert_eq!(game.name, "Quake");
+  }
+
+  #[tokio::test]
+  async fn giantbomb_errors_come_back_as_errors() {
+    let base = mock_giantbomb(vec![(
+      OK,
+      r#"{"error": "Invalid API Key", "status_code": 100, "number_of_total_results": 0, "results": []}"#,
+    )])
+    .await;
+
+    // not worth retrying, so the one canned answer is enough
+    let err = get_random_giantbomb_game(&quick_retry(3), &base, "bad", &GameFilter::default())
+      .await
+      .unwrap_err();
+    assert!(matches!(err, GotdError::Api { code: 100, .. }));
+    assert!(err.to_string().contains("Invalid API Key"));
+  }
+
+  #[tokio::test]
+  async fn rate_limits_and_server_errors_are_retried() {
+    let base = mock_giantbomb(vec![
+      ("429 Too Many Requests\r\nretry-after: 0", ""),
+      (OK, COUNT),
+      ("503 Service Unavailable", ""),
+      (OK, QUAKE),
+    ])
+    .await;
+
+    let game = get_random_giantbomb_game(&quick_retry(1), &base, "key", &GameFilter::default())
+      .await
+      .unwrap();
+    assert_eq!(game.name, "Quake");
+  }
+
+  #[tokio::test]
+  async fn retries_run_out() {
+    let base = mock_giantbomb(vec![
+      ("500 Internal Server Error", ""),
+      ("500 Internal Server Error", ""),
+    ])
+    .await;
+
+    let err = get_random_giantbomb_game(&quick_retry(1), &base, "key", &GameFilter::default())
+      .await
+      .unwrap_err();
+    assert!(matches!(err, GotdError::Status(500)));
+  }
+
+  #[tokio::test]
+  async fn slow_answers_time_out() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+      // hold on to the connection without ever answering
+      let (_socket, _) = listener.accept().await.unwrap();
+      tokio::time::sleep(Duration::from_secs(5)).await;
+    });
+
+    let mut retry = quick_retry(0);
+    retry.timeout = Duration::from_millis(50);
+    let err = get_random_giantbomb_game(
+      &retry,
+      &format!("http://{}/api", addr),
+      "key",
+      &GameFilter::default(),
+    )
+    .await
+    .unwrap_err();
+    match err {
+      GotdError::Transport(why) => assert!(why.is_timeout()),
+      other => panic!("expected a timeout, got {}", other),
+    }
+  }
+
+  #[tokio::test]
+  async fn transport_errors_leave_out_the_api_key() {
+    // nothing listens on the port once the listener is dropped
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+
+    let err = get_random_giantbomb_game(
+      &quick_retry(0),
+      &format!("http://{}/api", addr),
+      "sekrit-key",
+      &GameFilter::default(),
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(err, GotdError::Transport(_)));
+    assert!(!err.to_string().contains("sekrit-key"));
+    assert!(!format!("{:?}", err).contains("sekrit-key"));
+  }
+
+  #[test]
+  fn delays_double_up_to_the_max() {
+    let retry = Retry {
+      retries: 5,
+      base_delay: Duration::from_secs(1),
+      max_delay: Duration::from_secs(8),
+      timeout: Duration::from_secs(10),
+    };
+    let err = GotdError::Status(502);
+    let delays = (1..=6)
+      .map(|attempt| retry.next_delay(attempt, &err, 1.0))
+      .collect::<Vec<Option<Duration>>>();
+    assert_eq!(
+      delays,
+      vec![
+        Some(Duration::from_secs(1)),
+        Some(Duration::from_secs(2)),
+        Some(Duration::from_secs(4)),
+        Some(Duration::from_secs(8)),
+        Some(Duration::from_secs(8)),
+        None,
+      ]
+    );
+
+    // no jitter waits half as long
+    assert_eq!(retry.next_delay(3, &err, 0.0), Some(Duration::from_secs(2)));
+  }
+
+  #[test]
+  fn only_some_errors_are_retried() {
+    let retry = quick_retry(3);
+    assert!(retry.next_delay(1, &GotdError::Status(404), 1.0).is_none());
+    assert!(retry.next_delay(1, &GotdError::NoApiKey, 1.0).is_none());
+    assert!(retry
+      .next_delay(
+        1,
+        &GotdError::Api {
+          code: 100,
+          message: String::from("Invalid API Key")
+        },
+        1.0
+      )
+      .is_none());
+    assert!(retry
+      .next_delay(1, &GotdError::RateLimited(None), 1.0)
+      .is_some());
+  }
+
+  #[test]
+  fn retry_after_is_waited_out_unless_too_long() {
+    let retry = Retry {
+      retries: 3,
+      base_delay: Duration::from_secs(1),
+      max_delay: Duration::from_secs(30),
+      timeout: Duration::from_secs(10),
+    };
+    assert_eq!(
+      retry.next_delay(
+        1,
+        &GotdError::RateLimited(Some(Duration::from_secs(12))),
+        1.0
+      ),
+      Some(Duration::from_secs(12))
+    );
+    assert_eq!(
+      retry.next_delay(
+        1,
+        &GotdError::RateLimited(Some(Duration::from_secs(3600))),
+        1.0
+      ),
+      None
+    );
+  }
+
+  #[test]
+  fn parse_retry_after_reads_seconds_and_dates() {
+    let now = Utc.ymd(2015, 10, 21).and_hms(7, 28, 0);
+    assert_eq!(
+      parse_retry_after("120", now),
+      Some(Duration::from_secs(120))
+    );
+    assert_eq!(
+      parse_retry_after("Wed, 21 Oct 2015 07:28:30 GMT", now),
+      Some(Duration::from_secs(30))
+    );
+    assert_eq!(
+      parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now),
+      Some(Duration::from_secs(0))
+    );
+    assert_eq!(parse_retry_after("soon", now), None);
+  }
 }
@@ -1 +1,5 @@
+pub mod game_cache;
+pub mod game_filter;
+pub mod game_source;
 pub mod gotd;
+pub mod ops;
@@ -0,0 +1,157 @@
+use serenity::{http::Http, model::id::ChannelId};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tracing::{error, info};
+
+/**
+ * Where the bot tells whoever runs it what it's up to. Without a channel
+ * configured every notice is only logged.
+ */
+pub struct Ops {
+  channel: Option<ChannelId>,
+  // how many failures in a row before anyone hears about it
+  failure_threshold: u32,
+  // failures in a row of each thing that gets tried over and over
+  failures: Mutex<HashMap<String, u32>>,
+}
+
+/**
+ * What a call did to its run of failures
+ */
+#[derive(Debug, PartialEq)]
+enum Streak {
+  Unchanged,
+  Failing(u32),
+  Recovered(u32),
+}
+
+impl Ops {
+  pub fn new(channel: Option<u64>, failure_threshold: u32) -> Self {
+    Self {
+      channel: channel.map(ChannelId),
+      failure_threshold: failure_threshold.max(1),
+      failures: Mutex::new(HashMap::new()),
+    }
+  }
+
+  /**
+   * Post to the ops channel, if there is one. Failing to post is only
+   * logged, ops notices never get in the way of the bot.
+   */
+  pub async fn notify(&self, http: &Http, msg: String) {
+    info!("ops: {}", msg);
+    if let Some(channel) = self.channel {
+      if let Err(why) = channel.say(http, &msg).await {
+        error!("Failed to post to ops channel {}: {}", channel, why);
+      }
+    }
+  }
+
+  pub async fn startup(&self, http: &Http, shards: u64) {
+    self
+      .notify(
+        http,
+        format!(
+          "I'm alive! v{} running {} shard(s)",
+          env!("CARGO_PKG_VERSION"),
+          shards
+        ),
+      )
+      .await;
+  }
+
+  pub async fn shutdown(&self, http: &Http) {
+    self
+      .notify(
+        http,
+        format!("Shutting down v{}, bye!", env!("CARGO_PKG_VERSION")),
+      )
+      .await;
+  }
+
+  pub async fn scheduler_error(&self, http: &Http, what: &str, why: &str) {
+    self
+      .notify(http, format!("Scheduler failed to {}: {}", what, why))
+      .await;
+  }
+
+  /**
+   * Keep track of GiantBomb calls. Posts once when failures in a row reach
+   * the threshold, and again when a call finally works.
+   */
+  pub async fn giantbomb(&self, http: &Http, err: Option<String>) {
+    let msg = match self.track("giantbomb", err.is_none()) {
+      Streak::Failing(n) => format!(
+        "GiantBomb has failed {} times in a row, last error: {}",
+        n,
+        err.unwrap_or_default()
+      ),
+      Streak::Recovered(n) => format!("GiantBomb is answering again after {} failures", n),
+      Streak::Unchanged => return,
+    };
+    self.notify(http, msg).await;
+  }
+
+  /**
+   * Like `giantbomb`, for the scheduler's own db lookups that run every
+   * minute and would flood the channel through an outage
+   */
+  pub async fn scheduler_lookup(&self, http: &Http, what: &str, err: Option<String>) {
+    let msg = match self.track(what, err.is_none()) {
+      Streak::Failing(n) => format!(
+        "Scheduler failed to {} {} times in a row, last error: {}",
+        what,
+        n,
+        err.unwrap_or_default()
+      ),
+      Streak::Recovered(n) => format!("Scheduler can {} again after {} failures", what, n),
+      Streak::Unchanged => return,
+    };
+    self.notify(http, msg).await;
+  }
+
+  fn track(&self, key: &str, ok: bool) -> Streak {
+    let mut failures = self.failures.lock().unwrap();
+    let count = failures.entry(String::from(key)).or_insert(0);
+    let prev = *count;
+    *count = match ok {
+      true => 0,
+      false => prev + 1,
+    };
+    streak(prev, ok, self.failure_threshold)
+  }
+}
+
+fn streak(prev: u32, ok: bool, threshold: u32) -> Streak {
+  match ok {
+    true if prev >= threshold => Streak::Recovered(prev),
+    false if prev + 1 == threshold => Streak::Failing(prev + 1),
+    _ => Streak::Unchanged,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn streak_only_reports_crossing_the_threshold() {
+    assert_eq!(streak(0, false, 3), Streak::Unchanged);
+    assert_eq!(streak(1, false, 3), Streak::Unchanged);
+    assert_eq!(streak(2, false, 3), Streak::Failing(3));
+    assert_eq!(streak(3, false, 3), Streak::Unchanged);
+    assert_eq!(streak(5, true, 3), Streak::Recovered(5));
+    assert_eq!(streak(2, true, 3), Streak::Unchanged);
+  }
+
+  #[test]
+  fn track_counts_each_key_on_its_own() {
+    let ops = Ops::new(None, 2);
+    assert_eq!(ops.track("load schedules", false), Streak::Unchanged);
+    assert_eq!(ops.track("giantbomb", false), Streak::Unchanged);
+    assert_eq!(ops.track("load schedules", false), Streak::Failing(2));
+    assert_eq!(ops.track("load schedules", false), Streak::Unchanged);
+    assert_eq!(ops.track("load schedules", true), Streak::Recovered(3));
+    assert_eq!(ops.track("giantbomb", true), Streak::Unchanged);
+  }
+}
@@ -1,53 +1,56 @@
 use super::super::GotdMysqlStore;
-use super::respond;
+use super::{int_option, respond, string_option};
+use chrono::{Duration, Utc};
 use serenity::{
+  builder::CreateEmbed,
   http::Http,
   model::{id::ChannelId, interactions::application_command::ApplicationCommandInteraction},
   prelude::Context,
   utils::Colour,
 };
 use std::sync::Arc;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
-use crate::clients::gotd;
+use crate::clients::game_filter::GameFilter;
+use crate::clients::game_source::GameSource;
+use crate::clients::gotd::{self, Game, GotdError};
+use crate::clients::ops::Ops;
+use crate::config::Config;
+use crate::store::model::NewGotdHistory;
+use crate::store::storage::GotdDb;
+
+// how many random games to try before giving up on avoiding a repeat
+const MAX_PICKS: usize = 5;
+
+// the history column and embed field values both hold 1024 characters
+pub const PLATFORMS_MAX_LEN: usize = 1024;
 
 pub async fn handler(
   ctx: Arc<Context>,
-  _db: &GotdMysqlStore,
+  ops: &Ops,
+  games: &dyn GameSource,
   command: &ApplicationCommandInteraction,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-  respond(&ctx, command, String::from("Searching for game...")).await?;
+  let filter = match filter_options(command, games) {
+    Ok(f) => f,
+    Err(why) => {
+      respond(&ctx, command, why).await?;
+      return Ok(());
+    }
+  };
+  let searching = match filter.is_empty() {
+    true => String::from("Searching for game..."),
+    false => format!("Searching for game ({})...", filter),
+  };
+  respond(&ctx, command, searching).await?;
 
   // show to the users that andrew bot is thinking...
   let typing = command.channel_id.start_typing(&ctx.http);
-  match gotd::get_random_game().await {
+  match random_game(&ctx.http, ops, games, &filter).await {
     Ok(game) => {
-      let img = gotd::parse_image(&game);
-      let date = gotd::parse_date(&game);
       if let Err(why) = command
         .channel_id
-        .send_message(&ctx.http, |m| {
-          m.embed(|e| {
-            let plats = game
-              .platforms
-              .map(|ps| {
-                ps.iter()
-                  .map(|p| p.name.clone())
-                  .collect::<Vec<String>>()
-                  .join(", ")
-              })
-              .unwrap_or(String::from("No platforms"));
-            e.color(Colour::from(0x0099ff));
-            e.title(game.name);
-            e.author(|a| a.name("Game of the Day"));
-            e.url(game.site_detail_url.unwrap_or(String::from("")));
-            e.field("released", date, true);
-            e.field("platforms", plats, true);
-            e.description(game.deck.unwrap_or(String::from("")));
-            e.image(img);
-            e
-          })
-        })
+        .send_message(&ctx.http, |m| m.embed(|e| game_embed(e, &game)))
         .await
       {
         error!("Failed to respond {}", why);
@@ -77,53 +80,78 @@ pub async fn handler(
   Ok(())
 }
 
+/**
+ * Read the platform and decade options shared by `/game` and `/gotd`.
+ * A filter the game source can't serve is turned away here, rather than
+ * failing every time a game is picked.
+ */
+pub fn filter_options(
+  command: &ApplicationCommandInteraction,
+  games: &dyn GameSource,
+) -> Result<GameFilter, String> {
+  let options = &command.data.options;
+  let filter = GameFilter::parse(
+    string_option(options, "platform").as_deref(),
+    int_option(options, "decade"),
+  )?;
+  if !filter.is_empty() && !games.can_filter() {
+    return Err(String::from(
+      "I can only pick from any game right now, filtering needs a GIANTBOMB_API_KEY. Try again without `platform` or `decade`",
+    ));
+  }
+  Ok(filter)
+}
+
+/**
+ * Post a random game matching the filter to the channel. Returns an error
+ * when no game made it to the channel so the scheduler knows not to count
+ * it as sent.
+ */
 pub async fn send_gotd(
   http: &Arc<Http>,
-  _db: Arc<GotdMysqlStore>,
+  db: Arc<GotdMysqlStore>,
+  ops: &Ops,
+  config: &Config,
+  games: &dyn GameSource,
   channel_id: u64,
+  filter: &GameFilter,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
   let channel = ChannelId(channel_id);
   let typing = channel.start_typing(http);
-  match gotd::get_random_game().await {
+  let result = match get_fresh_game(http, &db, ops, config, games, channel_id, filter).await {
     Ok(game) => {
-      let img = gotd::parse_image(&game);
-      let date = gotd::parse_date(&game);
-      if let Err(why) = channel
-        .send_message(http, |m| {
-          m.embed(|e| {
-            let plats = game
-              .platforms
-              .map(|ps| {
-                ps.iter()
-                  .map(|p| p.name.clone())
-                  .collect::<Vec<String>>()
-                  .join(", ")
-              })
-              .unwrap_or(String::from("No platforms"));
-            e.color(Colour::from(0x0099ff));
-            e.title(game.name);
-            e.author(|a| a.name("Game of the Day"));
-            e.url(game.site_detail_url.unwrap_or(String::from("")));
-            e.field("released", date, true);
-            e.field("platforms", plats, true);
-            e.description(game.deck.unwrap_or(String::from("")));
-            e.image(img);
-            e
-          })
-        })
+      let entry = NewGotdHistory {
+        channel_id,
+        game_id: game.id,
+        game_guid: game.guid.clone(),
+        game_name: game.name.clone(),
+        site_detail_url: game.site_detail_url.clone(),
+        platforms: platform_names(&game, PLATFORMS_MAX_LEN),
+      };
+      match channel
+        .send_message(http, |m| m.embed(|e| game_embed(e, &game)))
         .await
       {
-        error!("Failed to respond for channel: {} {}", channel, why);
-        channel.say(http, "Bzzzrt! Failed to find game.").await?;
-      };
-
-      info!("Sent game for channel {}", channel);
+        Ok(_) => {
+          info!("Sent game for channel {}", channel);
+          if let Err(why) = db.save_history(entry) {
+            error!("Failed to save history for channel: {} {}", channel, why);
+          }
+          Ok(())
+        }
+        Err(why) => {
+          error!("Failed to respond for channel: {} {}", channel, why);
+          channel.say(http, "Bzzzrt! Failed to find game.").await?;
+          Err(Box::new(why) as Box<dyn std::error::Error + Send + Sync>)
+        }
+      }
     }
     Err(err) => {
       error!("Error fetching game for channel: {} {}", channel, err);
       channel.say(http, "Bzzzrt! Failed to find game.").await?;
+      Err(err)
     }
-  }
+  };
 
   match typing {
     Ok(t) => t.stop(),
@@ -133,5 +161,184 @@ pub async fn send_gotd(
     }
   };
 
-  Ok(())
+  result
+}
+
+/**
+ * Get a random game that hasn't been posted to the channel in the last
+ * `no_repeat_days` days. After a few rerolls just take whatever came
+ * back rather than posting nothing at all. Once GiantBomb has failed, the
+ * rerolls only come from the fallback so its retries are only paid once.
+ */
+async fn get_fresh_game(
+  http: &Http,
+  db: &GotdMysqlStore,
+  ops: &Ops,
+  config: &Config,
+  games: &dyn GameSource,
+  channel_id: u64,
+  filter: &GameFilter,
+) -> Result<Game, Box<dyn std::error::Error + Send + Sync>> {
+  let days = config.no_repeat_days;
+  let since = (Utc::now() - Duration::days(days)).naive_utc();
+
+  let (mut game, mut fell_back) = pick_game(http, ops, games, filter).await?;
+  for _ in 1..MAX_PICKS {
+    if !db.was_sent_since(channel_id, game.id, since)? {
+      return Ok(game);
+    }
+
+    info!(
+      "Game {} already sent to channel {} in the last {} days, picking again",
+      game.id, channel_id, days
+    );
+    if fell_back {
+      match games.fallback_game(filter).await {
+        Some(g) => game = g,
+        None => break,
+      }
+    } else {
+      let (g, f) = pick_game(http, ops, games, filter).await?;
+      game = g;
+      fell_back = f;
+    }
+  }
+
+  warn!(
+    "Gave up finding a fresh game for channel {} after {} picks",
+    channel_id, MAX_PICKS
+  );
+  Ok(game)
+}
+
+/**
+ * Get a random game matching the filter, letting ops know when GiantBomb
+ * keeps failing. When it does fail, fall back on a game the source has
+ * seen before.
+ */
+async fn random_game(
+  http: &Http,
+  ops: &Ops,
+  games: &dyn GameSource,
+  filter: &GameFilter,
+) -> Result<Game, Box<dyn std::error::Error + Send + Sync>> {
+  pick_game(http, ops, games, filter)
+    .await
+    .map(|(game, _)| game)
+}
+
+/**
+ * Same as `random_game`, but also says whether the game came from the
+ * fallback
+ */
+async fn pick_game(
+  http: &Http,
+  ops: &Ops,
+  games: &dyn GameSource,
+  filter: &GameFilter,
+) -> Result<(Game, bool), Box<dyn std::error::Error + Send + Sync>> {
+  let result = games.random_game(filter).await;
+  // nothing matching the filter isn't GiantBomb failing
+  let failure = result.as_ref().err().filter(|why| {
+    !matches!(
+      why.downcast_ref::<GotdError>(),
+      Some(GotdError::Empty(_)) | Some(GotdError::NoApiKey)
+    )
+  });
+  ops.giantbomb(http, failure.map(|e| e.to_string())).await;
+  match result {
+    Ok(game) => Ok((game, false)),
+    Err(why) => match games.fallback_game(filter).await {
+      Some(game) => {
+        warn!("Falling back on cached game {}: {}", game.id, why);
+        Ok((game, true))
+      }
+      None => Err(why),
+    },
+  }
+}
+
+/**
+ * The Game of the Day embed, the same wherever the game is shown
+ */
+pub fn game_embed<'a>(e: &'a mut CreateEmbed, game: &Game) -> &'a mut CreateEmbed {
+  let plats =
+    platform_names(game, PLATFORMS_MAX_LEN).unwrap_or_else(|| String::from("No platforms"));
+  e.color(Colour::from(0x0099ff));
+  e.title(&game.name);
+  e.author(|a| a.name("Game of the Day"));
+  e.url(game.site_detail_url.as_deref().unwrap_or(""));
+  e.field("released", gotd::parse_date(game), true);
+  e.field("platforms", plats, true);
+  e.description(game.deck.as_deref().unwrap_or(""));
+  e.image(gotd::parse_image(game));
+  e
+}
+
+/**
+ * The game's platform names on one line, no longer than `max`
+ */
+fn platform_names(game: &Game, max: usize) -> Option<String> {
+  game.platforms.as_ref().map(|ps| {
+    join_platforms(
+      &ps.iter().map(|p| p.name.as_str()).collect::<Vec<&str>>(),
+      max,
+    )
+  })
+}
+
+/**
+ * Join platform names with commas. When they don't all fit in `max`
+ * bytes, the rest are counted instead, like `PC, Xbox and 12 more`, and
+ * when not even the first one fits they're only counted.
+ */
+pub fn join_platforms(names: &[&str], max: usize) -> String {
+  let mut out = String::new();
+  for (idx, name) in names.iter().enumerate() {
+    let sep = match out.is_empty() {
+      true => "",
+      false => ", ",
+    };
+    let more = match idx + 1 == names.len() {
+      true => String::new(),
+      false => format!(" and {} more", names.len() - idx - 1),
+    };
+    if out.len() + sep.len() + name.len() + more.len() > max {
+      match out.is_empty() {
+        true => out = format!("{} platform(s)", names.len()),
+        false => out.push_str(&format!(" and {} more", names.len() - idx)),
+      }
+      break;
+    }
+    out.push_str(sep);
+    out.push_str(name);
+  }
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn join_platforms_fits_in_max() {
+    assert_eq!(join_platforms(&["PC", "Xbox"], 1024), "PC, Xbox");
+    assert_eq!(
+      join_platforms(&["PC", "Xbox", "PlayStation 4"], 20),
+      "PC, Xbox and 1 more"
+    );
+
+    // the first name doesn't need room for a comma
+    assert_eq!(join_platforms(&["PC"], 2), "PC");
+    assert_eq!(
+      join_platforms(&["Super Nintendo Entertainment System"], 20),
+      "1 platform(s)"
+    );
+    assert_eq!(join_platforms(&["PC", "Xbox"], 8), "2 platform(s)");
+
+    let many = vec!["PlayStation 4"; 200];
+    let joined = join_platforms(&many, PLATFORMS_MAX_LEN);
+    assert!(joined.len() <= PLATFORMS_MAX_LEN);
+    assert!(joined.ends_with(" more"));
+  }
 }
@@ -1,11 +1,11 @@
 use super::super::GotdMysqlStore;
-use super::respond;
-use chrono::{FixedOffset, Utc};
+use super::game::filter_options;
+use super::{respond, string_option};
+use chrono::{NaiveTime, Timelike, Utc};
+use chrono_tz::Tz;
 use cron::Schedule;
 use serenity::{
-  model::interactions::application_command::{
-    ApplicationCommandInteraction, ApplicationCommandInteractionDataOptionValue,
-  },
+  model::interactions::application_command::ApplicationCommandInteraction,
   prelude::Context,
   // utils::Colour,
 };
@@ -14,77 +14,361 @@ use std::sync::Arc;
 use tracing::{error, info};
 
 // use crate::clients::gotd;
-use crate::store::model::NewGotdJob;
+use crate::clients::game_filter::GameFilter;
+use crate::clients::game_source::GameSource;
+use crate::config::Config;
+use crate::store::model::{job_kind_label, GotdJob, NewGotdJob, JOB_KIND_GOTD};
 use crate::store::storage::GotdDb;
 
+// the shortest time a schedule may leave between two posts
+const MIN_CRON_INTERVAL_MINS: i64 = 60;
+
 pub async fn handler(
   ctx: Arc<Context>,
   db: &GotdMysqlStore,
+  config: &Config,
+  games: &dyn GameSource,
   command: &ApplicationCommandInteraction,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-  let options = command
-    .data
-    .options
-    .get(0)
-    .expect("Expected time")
-    .resolved
-    .as_ref()
-    .expect("Expected time str");
-
-  if let ApplicationCommandInteractionDataOptionValue::String(time_of_day) = options {
-    // default case is "night"
-    let cron_schedule = match time_of_day.as_str() {
-      // "morning" => String::from("0 8 18 * * * *"),
-      "morning" => String::from("0 0 8 * * * *"),
-      "noon" => String::from("0 0 12 * * * *"),
-      _ => String::from("0 0 20 * * * *"),
+  schedule(ctx, db, config, games, command, JOB_KIND_GOTD).await
+}
+
+/**
+ * Save a schedule of the given kind for the channel from the time, at,
+ * days, cron and timezone options. Shared by every scheduling command,
+ * only Game of the Day schedules read the game filter options.
+ */
+pub async fn schedule(
+  ctx: Arc<Context>,
+  db: &GotdMysqlStore,
+  config: &Config,
+  games: &dyn GameSource,
+  command: &ApplicationCommandInteraction,
+  kind: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+  let label = job_kind_label(kind);
+  let cron_schedule = match build_cron(
+    string_option(&command.data.options, "time").as_deref(),
+    string_option(&command.data.options, "at").as_deref(),
+    string_option(&command.data.options, "days").as_deref(),
+    string_option(&command.data.options, "cron").as_deref(),
+  ) {
+    Ok(c) => c,
+    Err(why) => {
+      respond(&ctx, command, why).await?;
+      return Ok(());
+    }
+  };
+
+  let timezone =
+    string_option(&command.data.options, "timezone").unwrap_or(config.default_timezone.clone());
+  if Tz::from_str(&timezone).is_err() {
+    let msg = format!(
+      "'{}' isn't a timezone I know. Try an IANA name like America/Los_Angeles or Europe/London",
+      timezone
+    );
+    respond(&ctx, command, msg).await?;
+    return Ok(());
+  }
+
+  let filter = match kind {
+    JOB_KIND_GOTD => match filter_options(command, games) {
+      Ok(f) => f,
+      Err(why) => {
+        respond(&ctx, command, why).await?;
+        return Ok(());
+      }
+    },
+    _ => GameFilter::default(),
+  };
+  let label = match filter.is_empty() {
+    true => String::from(label),
+    false => format!("{} ({})", label, filter),
+  };
+
+  let job = NewGotdJob {
+    channel_id: *command.channel_id.as_u64(),
+    guild_id: *command.guild_id.unwrap_or_default().as_u64(),
+    cron_schedule: cron_schedule.clone(),
+    created_by_id: *command.user.id.as_u64(),
+    timezone: timezone.clone(),
+    kind: String::from(kind),
+    game_platform: filter.platform.map(|p| String::from(p.key)),
+    game_decade: filter.decade,
+  };
+
+  // a channel can hold several schedules, but the same one twice is a mistake
+  let same = |s: &GotdJob| {
+    s.kind == kind
+      && s.cron_schedule == cron_schedule
+      && s.timezone == timezone
+      && s.game_filter().is_ok_and(|f| f == filter)
+  };
+  let scheds = db.get_active_sched(command.channel_id.0)?;
+  if let Some(s) = scheds.iter().find(|s| same(s)) {
+    let now = Utc::now();
+    let msg = match s.next_date_after(&now) {
+      Some(datetime) => format!(
+        "{} #{} already runs on that schedule in this channel. Next one sending on {}, ({}) mins",
+        label,
+        s.id,
+        datetime,
+        (datetime.with_timezone(&Utc) - now).num_minutes()
+      ),
+      None => format!(
+        "{} #{} already has that schedule in this channel",
+        label, s.id
+      ),
     };
+    respond(&ctx, command, msg).await?;
+    return Ok(());
+  }
+
+  if let Err(why) = db.save_sched(job) {
+    error!("Failed to insert data {}", why);
+    return Err(why);
+  };
+
+  info!(
+    "User {} created {} GotdJob for channel {} with sched {} ({}) for {}",
+    command.user.id, kind, command.channel_id, cron_schedule, timezone, filter
+  );
+
+  // mysql won't hand back the new id on insert, so look it up again
+  let saved_id = db
+    .get_active_sched(command.channel_id.0)?
+    .iter()
+    .filter(|s| same(s))
+    .map(|s| s.id)
+    .max();
+
+  let msg = match saved_id {
+    Some(saved) => format!(
+      "Gotcha, scheduling {} #{} for `{}` ({}). This channel now has {} schedule(s)",
+      label,
+      saved,
+      cron_schedule,
+      timezone,
+      scheds.len() + 1
+    ),
+    None => format!(
+      "Gotcha, scheduling {} for `{}` ({})",
+      label, cron_schedule, timezone
+    ),
+  };
+  respond(&ctx, command, msg).await?;
+
+  Ok(())
+}
 
-    let job = NewGotdJob {
-      channel_id: *command.channel_id.as_u64(),
-      guild_id: *command.guild_id.unwrap_or_default().as_u64(),
-      cron_schedule: cron_schedule.clone(),
-      created_by_id: *command.user.id.as_u64(),
+/**
+ * Turn the user's options into a cron expression. A raw `cron` wins over
+ * `at`, which wins over the preset `time` of day. Every expression is run
+ * through `Schedule::from_str` so nothing invalid makes it to the database.
+ */
+fn build_cron(
+  time: Option<&str>,
+  at: Option<&str>,
+  days: Option<&str>,
+  cron: Option<&str>,
+) -> Result<String, String> {
+  if let Some(c) = cron {
+    let c = c.trim();
+    return match Schedule::from_str(c) {
+      Ok(s) => check_schedule(&s).map(|_| String::from(c)),
+      Err(why) => Err(format!(
+        "`{}` isn't a cron expression I understand ({}). Use `sec min hour day month weekday year`, like `0 30 9 * * Mon-Fri *`",
+        c, why
+      )),
     };
+  }
 
-    // check if a sched exists; if it does report it!
-    let sched = db.get_active_sched(command.channel_id.0)?;
-    if sched.is_some() {
-      let s = sched.unwrap();
-      let schedule = Schedule::from_str(&s.cron_schedule).unwrap();
-      let tz = FixedOffset::west(5 * 3600);
-      let datetime = schedule.upcoming(tz).take(1).next().unwrap();
-      let now = Utc::now().with_timezone(&tz);
-      let diff = datetime - now;
-
-      let msg = format!(
-        "Gotd already set up for this channel. Next game sending on {}, ({}) mins",
-        datetime,
-        diff.num_minutes()
-      );
-      respond(&ctx, command, String::from(msg)).await?;
-      return Ok(());
+  let (hour, minute) = match (at, time) {
+    (Some(a), _) => match NaiveTime::parse_from_str(a.trim(), "%H:%M") {
+      Ok(t) => (t.hour(), t.minute()),
+      Err(_) => {
+        return Err(format!(
+          "`{}` isn't a time I understand, use HH:MM like 09:30",
+          a
+        ))
+      }
+    },
+    // default case is "night"
+    (None, Some(t)) => match t {
+      "morning" => (8, 0),
+      "noon" => (12, 0),
+      _ => (20, 0),
+    },
+    (None, None) => {
+      return Err(String::from(
+        "Tell me when! Pick a time, an exact time with `at`, or a `cron` expression",
+      ))
     }
+  };
 
-    if let Err(why) = db.save_sched(job) {
-      error!("Failed to insert data {}", why);
-      return Err(why);
-    };
+  let weekdays = parse_days(days.unwrap_or("daily"))?;
+  let expr = format!("0 {} {} * * {} *", minute, hour, weekdays);
+  match Schedule::from_str(&expr) {
+    Ok(s) => check_schedule(&s).map(|_| expr),
+    Err(why) => Err(format!(
+      "Couldn't build a schedule from those options ({})",
+      why
+    )),
+  }
+}
 
-    info!(
-      "User {} created GotdJob for channel {} with sched {}",
-      command.user.id, command.channel_id, cron_schedule
+/**
+ * A schedule has to fire again, and not more often than
+ * `MIN_CRON_INTERVAL_MINS`, checked over its next few runs
+ */
+fn check_schedule(schedule: &Schedule) -> Result<(), String> {
+  let upcoming = schedule.upcoming(Utc).take(10).collect::<Vec<_>>();
+  if upcoming.is_empty() {
+    return Err(String::from(
+      "That schedule never runs again, check the year field",
+    ));
+  }
+
+  let min = chrono::Duration::minutes(MIN_CRON_INTERVAL_MINS);
+  if upcoming.windows(2).any(|w| w[1] - w[0] < min) {
+    return Err(format!(
+      "That schedule runs too often, leave at least {} minutes between posts",
+      MIN_CRON_INTERVAL_MINS
+    ));
+  }
+  Ok(())
+}
+
+/**
+ * Parse the `days` option into the day-of-week field of a cron expression
+ */
+fn parse_days(days: &str) -> Result<String, String> {
+  let names = [
+    ("Mon", "monday"),
+    ("Tue", "tuesday"),
+    ("Wed", "wednesday"),
+    ("Thu", "thursday"),
+    ("Fri", "friday"),
+    ("Sat", "saturday"),
+    ("Sun", "sunday"),
+  ];
+  match days.trim().to_lowercase().as_str() {
+    "" | "*" | "daily" | "every day" | "everyday" => Ok(String::from("*")),
+    "weekdays" => Ok(String::from("Mon-Fri")),
+    "weekends" => Ok(String::from("Sat,Sun")),
+    list => {
+      let mut parsed = Vec::<&str>::new();
+      for day in list.split(|c: char| c == ',' || c.is_whitespace()) {
+        if day.is_empty() {
+          continue;
+        }
+
+        match names
+          .iter()
+          .find(|(short, long)| day == short.to_lowercase() || day == *long)
+        {
+          Some((n, _)) if !parsed.contains(n) => parsed.push(*n),
+          Some(_) => {}
+          None => {
+            return Err(format!(
+              "`{}` isn't a day I know. Use daily, weekdays, weekends or a list like Mon,Wed,Fri",
+              day
+            ))
+          }
+        }
+      }
+
+      if parsed.is_empty() {
+        return Ok(String::from("*"));
+      }
+      Ok(parsed.join(","))
+    }
+  }
+}
+
+/**
+ * Describe a cron expression in human terms when it is one of the shapes
+ * `/gotd` builds, otherwise fall back to the raw expression
+ */
+pub fn describe_cron(expr: &str) -> String {
+  let parts = expr.split_whitespace().collect::<Vec<&str>>();
+  if let ["0", min, hour, "*", "*", weekdays, "*"] = parts.as_slice() {
+    if let (Ok(h), Ok(m)) = (hour.parse::<u32>(), min.parse::<u32>()) {
+      let days = match *weekdays {
+        "*" => String::from("Every day"),
+        "Mon-Fri" => String::from("Weekdays"),
+        "Sat,Sun" => String::from("Weekends"),
+        list => format!("Every {}", list.replace(",", ", ")),
+      };
+      return format!("{} at {:02}:{:02}", days, h, m);
+    }
+  }
+
+  format!("`{}`", expr)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn build_cron_uses_preset_time_of_day() {
+    assert_eq!(
+      build_cron(Some("morning"), None, None, None),
+      Ok(String::from("0 0 8 * * * *"))
+    );
+  }
+
+  #[test]
+  fn build_cron_prefers_exact_time_and_days() {
+    assert_eq!(
+      build_cron(Some("morning"), Some("09:30"), Some("weekdays"), None),
+      Ok(String::from("0 30 9 * * Mon-Fri *"))
     );
+    assert_eq!(
+      build_cron(None, Some("17:05"), Some("mon, Wednesday,fri"), None),
+      Ok(String::from("0 5 17 * * Mon,Wed,Fri *"))
+    );
+  }
 
-    respond(
-      &ctx,
-      command,
-      String::from(format!("Gotcha, scheduling for {}", time_of_day).as_str()),
-    )
-    .await?;
-  } else {
-    respond(&ctx, command, String::from("Not a valid time of day")).await?;
+  #[test]
+  fn build_cron_accepts_valid_raw_cron() {
+    assert_eq!(
+      build_cron(None, None, None, Some(" 0 15 10 * * Sat * ")),
+      Ok(String::from("0 15 10 * * Sat *"))
+    );
   }
 
-  Ok(())
+  #[test]
+  fn build_cron_rejects_invalid_input() {
+    assert!(build_cron(None, None, None, Some("every tuesday")).is_err());
+    assert!(build_cron(None, Some("25:00"), None, None).is_err());
+    assert!(build_cron(None, Some("09:30"), Some("funday"), None).is_err());
+    assert!(build_cron(None, Some("09:30"), Some("monkey"), None).is_err());
+    assert!(build_cron(None, Some("09:30"), Some("wedding"), None).is_err());
+    assert!(build_cron(None, None, None, None).is_err());
+  }
+
+  #[test]
+  fn build_cron_rejects_schedules_that_never_run_or_run_too_often() {
+    assert!(build_cron(None, None, None, Some("0 0 8 * * * 2020")).is_err());
+    assert!(build_cron(None, None, None, Some("* * * * * * *")).is_err());
+    assert!(build_cron(None, None, None, Some("0 0/15 * * * * *")).is_err());
+    assert!(build_cron(None, None, None, Some("0 0 8,12 * * * *")).is_ok());
+  }
+
+  #[test]
+  fn describe_cron_humanizes_built_schedules() {
+    assert_eq!(describe_cron("0 0 8 * * * *"), "Every day at 08:00");
+    assert_eq!(describe_cron("0 30 9 * * Mon-Fri *"), "Weekdays at 09:30");
+    assert_eq!(
+      describe_cron("0 5 17 * * Mon,Wed,Fri *"),
+      "Every Mon, Wed, Fri at 17:05"
+    );
+  }
+
+  #[test]
+  fn describe_cron_falls_back_to_raw_expression() {
+    assert_eq!(describe_cron("0 0/15 * * * * *"), "`0 0/15 * * * * *`");
+  }
 }
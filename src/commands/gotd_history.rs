@@ -0,0 +1,262 @@
+use super::super::GotdMysqlStore;
+use super::game::{game_embed, join_platforms, PLATFORMS_MAX_LEN};
+use super::{int_option, respond};
+use crate::clients::game_source::GameSource;
+use crate::store::model::GotdHistory;
+use crate::store::storage::GotdDb;
+use serenity::{
+  builder::{CreateComponents, CreateEmbed},
+  model::interactions::{
+    application_command::ApplicationCommandInteraction,
+    message_component::{ButtonStyle, MessageComponentInteraction},
+    InteractionResponseType,
+  },
+  prelude::Context,
+  utils::Colour,
+};
+use std::sync::Arc;
+use tracing::warn;
+
+const PAGE_SIZE: i64 = 5;
+
+// discord won't send an embed with a field name longer than this
+const FIELD_NAME_MAX_LEN: usize = 256;
+
+pub async fn handler(
+  ctx: Arc<Context>,
+  db: &GotdMysqlStore,
+  games: &dyn GameSource,
+  command: &ApplicationCommandInteraction,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+  let channel = command.channel_id.0;
+  let total = db.count_history(channel)?;
+  if total == 0 {
+    respond(
+      &ctx,
+      command,
+      String::from("No Game of the Day has been posted to this channel yet."),
+    )
+    .await?;
+    return Ok(());
+  }
+
+  if let Some(number) = int_option(&command.data.options, "number") {
+    return show_game(ctx, db, games, command, number, total).await;
+  }
+
+  let pages = page_count(total);
+  let entries = db.get_history(channel, 0, PAGE_SIZE)?;
+
+  command
+    .create_interaction_response(&ctx.http, |res| {
+      res
+        .kind(InteractionResponseType::ChannelMessageWithSource)
+        .interaction_response_data(|m| {
+          m.create_embed(|e| history_embed(e, &entries, 0, pages))
+            .components(|c| history_buttons(c, 0, pages))
+        })
+    })
+    .await?;
+
+  Ok(())
+}
+
+/**
+ * Handles the previous/next buttons. The custom id looks like
+ * `gotd-history::<page>` where page is the one to show
+ */
+pub async fn page_handler(
+  ctx: Arc<Context>,
+  db: &GotdMysqlStore,
+  component: &MessageComponentInteraction,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+  let channel = component.channel_id.0;
+  let total = db.count_history(channel)?;
+  let pages = page_count(total);
+
+  // the history may have grown since the buttons were drawn, so clamp
+  let page = component
+    .data
+    .custom_id
+    .split("::")
+    .nth(1)
+    .and_then(|p| p.parse::<i64>().ok())
+    .unwrap_or(0)
+    .max(0)
+    .min(pages - 1);
+  let entries = db.get_history(channel, page * PAGE_SIZE, PAGE_SIZE)?;
+
+  component
+    .create_interaction_response(&ctx.http, |res| {
+      res
+        .kind(InteractionResponseType::UpdateMessage)
+        .interaction_response_data(|m| {
+          m.create_embed(|e| history_embed(e, &entries, page, pages))
+            .components(|c| history_buttons(c, page, pages))
+        })
+    })
+    .await?;
+
+  Ok(())
+}
+
+/**
+ * Show the numbered game from the history again, as it was first posted
+ */
+async fn show_game(
+  ctx: Arc<Context>,
+  db: &GotdMysqlStore,
+  games: &dyn GameSource,
+  command: &ApplicationCommandInteraction,
+  number: i64,
+  total: i64,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+  if number < 1 || number > total {
+    let msg = format!(
+      "Pick a `number` from 1 to {}, newest first. `/gotd-history` lists them.",
+      total
+    );
+    respond(&ctx, command, msg).await?;
+    return Ok(());
+  }
+
+  let entry = match db.get_history(command.channel_id.0, number - 1, 1)?.pop() {
+    Some(entry) => entry,
+    None => {
+      respond(
+        &ctx,
+        command,
+        format!("No game #{} in this channel.", number),
+      )
+      .await?;
+      return Ok(());
+    }
+  };
+  let game = match games.game_by_id(entry.game_id).await {
+    Ok(game) => game,
+    Err(why) => {
+      warn!("Failed to look up game {}: {}", entry.game_id, why);
+      None
+    }
+  };
+
+  command
+    .create_interaction_response(&ctx.http, |res| {
+      res
+        .kind(InteractionResponseType::ChannelMessageWithSource)
+        .interaction_response_data(|m| {
+          m.create_embed(|e| match &game {
+            Some(game) => game_embed(e, game),
+            None => entry_embed(e, &entry),
+          })
+        })
+    })
+    .await?;
+
+  Ok(())
+}
+
+/**
+ * Whatever the history row remembers, for games that can't be found any more
+ */
+fn entry_embed<'a>(e: &'a mut CreateEmbed, entry: &GotdHistory) -> &'a mut CreateEmbed {
+  e.color(Colour::from(0x0099ff));
+  e.title(&entry.game_name);
+  e.author(|a| a.name("Game of the Day"));
+  e.url(entry.site_detail_url.as_deref().unwrap_or(""));
+  e.field("posted", entry.sent_on_ts.format("%b %e, %Y"), true);
+  e.field(
+    "platforms",
+    entry.platforms.as_deref().unwrap_or("No platforms"),
+    true,
+  );
+  e
+}
+
+fn page_count(total: i64) -> i64 {
+  ((total + PAGE_SIZE - 1) / PAGE_SIZE).max(1)
+}
+
+fn history_embed<'a>(
+  e: &'a mut CreateEmbed,
+  entries: &[GotdHistory],
+  page: i64,
+  pages: i64,
+) -> &'a mut CreateEmbed {
+  e.color(Colour::from(0x0099ff));
+  e.title("Game of the Day history");
+  for (i, entry) in entries.iter().enumerate() {
+    let link = entry.site_detail_url.as_deref().unwrap_or("No link");
+    // the platforms share the field with the link
+    let plats = match entry.platforms.as_deref() {
+      Some(p) => join_platforms(
+        &p.split(", ").collect::<Vec<&str>>(),
+        PLATFORMS_MAX_LEN.saturating_sub(link.len() + 1),
+      ),
+      None => String::from("No platforms"),
+    };
+    let prefix = format!(
+      "{}. {} - ",
+      page * PAGE_SIZE + i as i64 + 1,
+      entry.sent_on_ts.format("%b %e, %Y")
+    );
+    e.field(
+      field_name(&prefix, &entry.game_name),
+      format!("{}\n{}", plats, link),
+      false,
+    );
+  }
+  e.footer(|f| f.text(format!("Page {} of {}", page + 1, pages)));
+  e
+}
+
+/**
+ * The prefix and as much of the game name as fits in a field name, cut
+ * short with an ellipsis
+ */
+fn field_name(prefix: &str, game_name: &str) -> String {
+  let room = FIELD_NAME_MAX_LEN.saturating_sub(prefix.chars().count());
+  if game_name.chars().count() <= room {
+    return format!("{}{}", prefix, game_name);
+  }
+
+  let cut = game_name
+    .chars()
+    .take(room.saturating_sub(1))
+    .collect::<String>();
+  format!("{}{}…", prefix, cut)
+}
+
+fn history_buttons(c: &mut CreateComponents, page: i64, pages: i64) -> &mut CreateComponents {
+  c.create_action_row(|a| {
+    a.create_button(|b| {
+      b.label("Previous")
+        .custom_id(format!("gotd-history::{}", page - 1))
+        .style(ButtonStyle::Secondary)
+        .disabled(page <= 0)
+    });
+    a.create_button(|b| {
+      b.label("Next")
+        .custom_id(format!("gotd-history::{}", page + 1))
+        .style(ButtonStyle::Secondary)
+        .disabled(page + 1 >= pages)
+    })
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn field_name_fits_long_game_names() {
+    assert_eq!(
+      field_name("1. Oct 18, 2021 - ", "Doom"),
+      "1. Oct 18, 2021 - Doom"
+    );
+
+    let name = field_name("12. Oct 18, 2021 - ", &"é".repeat(255));
+    assert_eq!(name.chars().count(), FIELD_NAME_MAX_LEN);
+    assert!(name.ends_with("é…"));
+  }
+}
@@ -0,0 +1,107 @@
+use super::super::GotdMysqlStore;
+use super::gotd::describe_cron;
+use super::respond;
+use crate::store::model::job_kind_label;
+use crate::store::storage::GotdDb;
+use chrono::Utc;
+use serenity::{
+  model::interactions::{
+    application_command::ApplicationCommandInteraction, InteractionResponseType,
+  },
+  prelude::Context,
+  utils::Colour,
+};
+use std::sync::Arc;
+use tracing::error;
+
+// discord won't render more than 25 fields on an embed
+const MAX_FIELDS: usize = 25;
+
+pub async fn handler(
+  ctx: Arc<Context>,
+  db: &GotdMysqlStore,
+  command: &ApplicationCommandInteraction,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+  let guild_id = match command.guild_id {
+    Some(g) => g,
+    None => {
+      respond(
+        &ctx,
+        command,
+        String::from("Schedules only live in servers."),
+      )
+      .await?;
+      return Ok(());
+    }
+  };
+
+  let scheds = db.get_all_active_sched_for_guild(guild_id.0)?;
+  if scheds.is_empty() {
+    respond(
+      &ctx,
+      command,
+      String::from("Nothing scheduled anywhere in this server."),
+    )
+    .await?;
+    return Ok(());
+  }
+
+  let now = Utc::now();
+  let total = scheds.len();
+
+  if let Err(why) = command
+    .create_interaction_response(&ctx.http, |res| {
+      res
+        .kind(InteractionResponseType::ChannelMessageWithSource)
+        .interaction_response_data(|m| {
+          m.create_embed(|e| {
+            e.color(Colour::from(0x0099ff));
+            e.title("Scheduled posts");
+            for s in scheds.iter().take(MAX_FIELDS) {
+              let next = match s.next_date_after(&now) {
+                Some(d) => format!("<t:{}:F> (<t:{}:R>)", d.timestamp(), d.timestamp()),
+                None => String::from("never"),
+              };
+              let created = s
+                .created_on_ts
+                .map(|ts| ts.format("%b %e, %Y").to_string())
+                .unwrap_or(String::from("unknown"));
+
+              let name = match s.game_filter() {
+                Ok(filter) if filter.is_empty() => format!("#{} {}", s.id, job_kind_label(&s.kind)),
+                Ok(filter) => format!("#{} {} ({})", s.id, job_kind_label(&s.kind), filter),
+                Err(_) => format!(
+                  "#{} {} (filter no longer works)",
+                  s.id,
+                  job_kind_label(&s.kind)
+                ),
+              };
+              e.field(
+                name,
+                format!(
+                  "<#{}>\n{} ({})\nby <@{}> on {}\nnext: {}",
+                  s.channel_id,
+                  describe_cron(&s.cron_schedule),
+                  s.timezone,
+                  s.created_by_id,
+                  created,
+                  next
+                ),
+                false,
+              );
+            }
+            if total > MAX_FIELDS {
+              e.footer(|f| f.text(format!("and {} more...", total - MAX_FIELDS)));
+            }
+            e
+          })
+        })
+    })
+    .await
+  {
+    error!("Failed to respond {}", why);
+    return Err(Box::new(why));
+  }
+
+  Ok(())
+}
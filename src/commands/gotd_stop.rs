@@ -1,5 +1,6 @@
 use super::super::GotdMysqlStore;
-use super::respond;
+use super::{int_option, respond};
+use crate::store::model::{job_kind_label, GotdJob};
 use crate::store::storage::GotdDb;
 use serenity::{
   model::interactions::application_command::ApplicationCommandInteraction, prelude::Context,
@@ -12,23 +13,59 @@ pub async fn handler(
   db: &GotdMysqlStore,
   command: &ApplicationCommandInteraction,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-  let opt = db.get_active_sched(command.channel_id.0)?;
+  let scheds = db.get_active_sched(command.channel_id.0)?;
 
-  if opt.is_none() {
+  if scheds.is_empty() {
     respond(
       &ctx,
       command,
-      String::from("No active Game of the Day found for this channel."),
+      String::from("No active schedule found for this channel."),
     )
     .await?;
     return Ok(());
   }
 
-  let job = opt.unwrap();
+  let requested_id = int_option(&command.data.options, "id");
+
+  // without an id we can only guess when there is exactly one schedule
+  let job = match requested_id {
+    Some(job_id) => match scheds.iter().find(|s| s.id as i64 == job_id) {
+      Some(s) => s,
+      None => {
+        let msg = format!(
+          "No schedule #{} in this channel. Pick one of:\n{}",
+          job_id,
+          describe_all(&scheds)
+        );
+        respond(&ctx, command, msg).await?;
+        return Ok(());
+      }
+    },
+    None if scheds.len() == 1 => &scheds[0],
+    None => {
+      let msg = format!(
+        "This channel has {} schedules, tell me which `id` to stop:\n{}",
+        scheds.len(),
+        describe_all(&scheds)
+      );
+      respond(&ctx, command, msg).await?;
+      return Ok(());
+    }
+  };
+
   let did_delete = db.delete_sched(job.id)?;
 
   if did_delete {
-    respond(&ctx, command, String::from("No more games for these days!")).await?;
+    respond(
+      &ctx,
+      command,
+      format!(
+        "No more {} for these days! Stopped #{}",
+        job_kind_label(&job.kind),
+        job.id
+      ),
+    )
+    .await?;
   } else {
     respond(
       &ctx,
@@ -44,3 +81,19 @@ pub async fn handler(
 
   Ok(())
 }
+
+fn describe_all(scheds: &[GotdJob]) -> String {
+  scheds
+    .iter()
+    .map(|s| {
+      format!(
+        "`{}`: {} `{}` ({})",
+        s.id,
+        job_kind_label(&s.kind),
+        s.cron_schedule,
+        s.timezone
+      )
+    })
+    .collect::<Vec<String>>()
+    .join("\n")
+}
@@ -1,9 +1,20 @@
 pub mod ping;
 
 use super::GotdMysqlStore;
+use crate::clients::game_source::GameSource;
+use crate::clients::ops::Ops;
+use crate::config::Config;
+use crate::store::model::JOB_KIND_WYR;
+use crate::store::react_store::ReactStore;
+use crate::store::wyr_store::WyrStore;
 use serenity::{
   model::interactions::{
-    application_command::ApplicationCommandInteraction, InteractionResponseType,
+    application_command::{
+      ApplicationCommandInteraction, ApplicationCommandInteractionDataOption,
+      ApplicationCommandInteractionDataOptionValue,
+    },
+    message_component::MessageComponentInteraction,
+    InteractionResponseType,
   },
   prelude::Context,
 };
@@ -12,27 +23,79 @@ use tracing::error;
 
 pub mod game;
 mod gotd;
+mod gotd_history;
+mod gotd_list;
 mod gotd_stop;
 mod mem;
-mod wyr;
+mod react_rule;
+pub mod wyr;
+mod wyr_reload;
+mod wyr_stats;
+mod wyr_submit;
+
+/**
+ * What the commands share, built once at startup
+ */
+pub struct CommandState {
+  pub db: Arc<GotdMysqlStore>,
+  pub wyr: Arc<WyrStore>,
+  pub react: Arc<ReactStore>,
+  pub ops: Arc<Ops>,
+  pub config: Arc<Config>,
+  pub games: Arc<dyn GameSource>,
+}
 
 pub async fn handler(
   ctx: Arc<Context>,
-  db: &GotdMysqlStore,
+  state: &CommandState,
   command: &ApplicationCommandInteraction,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+  let db = state.db.as_ref();
+  let wyr_store = state.wyr.as_ref();
+  let react_store = state.react.as_ref();
+  let ops = state.ops.as_ref();
+  let config = state.config.as_ref();
+  let games = state.games.as_ref();
   match command.data.name.as_str() {
     "mem" => mem::handler(ctx, db, command).await?,
-    "game" => game::handler(ctx, db, command).await?,
-    "gotd" => gotd::handler(ctx, db, command).await?,
+    "game" => game::handler(ctx, ops, games, command).await?,
+    "gotd" => gotd::handler(ctx, db, config, games, command).await?,
     "gotd-stop" => gotd_stop::handler(ctx, db, command).await?,
-    "wyr" => wyr::handler(ctx, db, command).await?,
+    "gotd-list" => gotd_list::handler(ctx, db, command).await?,
+    "gotd-history" => gotd_history::handler(ctx, db, games, command).await?,
+    "wyr" => wyr::handler(ctx, db, wyr_store, command).await?,
+    "wyr-reload" => wyr_reload::handler(ctx, wyr_store, command).await?,
+    "wyr-submit" => wyr_submit::handler(ctx, db, command).await?,
+    "wyr-stats" => wyr_stats::handler(ctx, db, command).await?,
+    "wyr-schedule" => gotd::schedule(ctx, db, config, games, command, JOB_KIND_WYR).await?,
+    "react-rule" => react_rule::handler(ctx, db, react_store, command).await?,
     _ => error!("Unknown slash command"),
   };
 
   Ok(())
 }
 
+/**
+ * Route button clicks by the first segment of their custom id, which is
+ * always `<kind>::...`
+ */
+pub async fn component_handler(
+  ctx: Arc<Context>,
+  state: &CommandState,
+  component: &MessageComponentInteraction,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+  let db = state.db.as_ref();
+  let wyr_store = state.wyr.as_ref();
+  match component.data.custom_id.split("::").next() {
+    Some("wyr") => wyr::vote_handler(ctx, db, wyr_store, component).await?,
+    Some("wyr-submit") => wyr_submit::review_handler(ctx, db, component).await?,
+    Some("gotd-history") => gotd_history::page_handler(ctx, db, component).await?,
+    _ => error!("Unknown component {}", component.data.custom_id),
+  };
+
+  Ok(())
+}
+
 pub async fn respond(
   ctx: &Arc<Context>,
   command: &ApplicationCommandInteraction,
@@ -47,3 +110,34 @@ pub async fn respond(
     .await?;
   Ok(())
 }
+
+/**
+ * Find an option by name, if the user supplied it. Takes the options
+ * rather than the command so subcommands can pass their own.
+ */
+pub fn option<'a>(
+  options: &'a [ApplicationCommandInteractionDataOption],
+  name: &str,
+) -> Option<&'a ApplicationCommandInteractionDataOptionValue> {
+  options
+    .iter()
+    .find(|o| o.name == name)
+    .and_then(|o| o.resolved.as_ref())
+}
+
+pub fn string_option(
+  options: &[ApplicationCommandInteractionDataOption],
+  name: &str,
+) -> Option<String> {
+  match option(options, name) {
+    Some(ApplicationCommandInteractionDataOptionValue::String(s)) => Some(s.clone()),
+    _ => None,
+  }
+}
+
+pub fn int_option(options: &[ApplicationCommandInteractionDataOption], name: &str) -> Option<i64> {
+  match option(options, name) {
+    Some(ApplicationCommandInteractionDataOptionValue::Integer(i)) => Some(*i),
+    _ => None,
+  }
+}
@@ -0,0 +1,175 @@
+use super::super::GotdMysqlStore;
+use super::{int_option, respond, string_option};
+use crate::store::model::{NewReactRule, ReactRule};
+use crate::store::react_store::{ReactStore, Rule};
+use crate::store::storage::ReactDb;
+use serenity::{
+  model::interactions::{
+    application_command::{ApplicationCommandInteraction, ApplicationCommandInteractionDataOption},
+    InteractionResponseType,
+  },
+  prelude::Context,
+  utils::Colour,
+};
+use std::sync::Arc;
+use tracing::info;
+
+// an embed holds at most 25 fields
+const MAX_FIELDS: usize = 25;
+
+/**
+ * `/react-rule add|remove|list`. Anyone can list the guild's rules, only
+ * moderators can change them.
+ */
+pub async fn handler(
+  ctx: Arc<Context>,
+  db: &GotdMysqlStore,
+  react_store: &ReactStore,
+  command: &ApplicationCommandInteraction,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+  let guild_id = match command.guild_id {
+    Some(g) => g.0,
+    None => {
+      respond(
+        &ctx,
+        command,
+        String::from("React rules only work in a server."),
+      )
+      .await?;
+      return Ok(());
+    }
+  };
+  let sub = match command.data.options.first() {
+    Some(s) => s,
+    None => return Ok(()),
+  };
+
+  let is_moderator = command
+    .member
+    .as_ref()
+    .and_then(|m| m.permissions)
+    .map(|p| p.manage_messages())
+    .unwrap_or(false);
+  if sub.name != "list" && !is_moderator {
+    respond(
+      &ctx,
+      command,
+      String::from("Only moderators can change react rules."),
+    )
+    .await?;
+    return Ok(());
+  }
+
+  let msg = match sub.name.as_str() {
+    "add" => add(db, react_store, command, guild_id, &sub.options)?,
+    "remove" => remove(db, react_store, command, guild_id, &sub.options)?,
+    _ => return list(ctx, db, command, guild_id).await,
+  };
+  respond(&ctx, command, msg).await
+}
+
+fn add(
+  db: &GotdMysqlStore,
+  react_store: &ReactStore,
+  command: &ApplicationCommandInteraction,
+  guild_id: u64,
+  options: &[ApplicationCommandInteractionDataOption],
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+  let trigger = string_option(options, "trigger").unwrap_or_default();
+  let value = string_option(options, "value").unwrap_or_default();
+  let reactions = string_option(options, "reactions").unwrap_or_default();
+
+  // check it the same way it'll be loaded so a bad rule never gets saved
+  if let Err(why) = Rule::parse(0, &trigger, &value, &reactions) {
+    return Ok(why);
+  }
+
+  db.save_react_rule(NewReactRule {
+    guild_id,
+    trigger_kind: trigger.clone(),
+    trigger_value: String::from(value.trim()),
+    reactions: reactions
+      .split_whitespace()
+      .collect::<Vec<&str>>()
+      .join(" "),
+    created_by_id: command.user.id.0,
+  })?;
+  let rules = db.get_react_rules(guild_id)?;
+  react_store.set_guild(guild_id, &rules);
+
+  info!(
+    "User {} added {} react rule for `{}` in guild {}",
+    command.user.id, trigger, value, guild_id
+  );
+  Ok(match rules.last() {
+    Some(r) => format!("Gotcha, added react rule #{}: {}", r.id, describe(r)),
+    None => String::from("Gotcha, added the react rule."),
+  })
+}
+
+fn remove(
+  db: &GotdMysqlStore,
+  react_store: &ReactStore,
+  command: &ApplicationCommandInteraction,
+  guild_id: u64,
+  options: &[ApplicationCommandInteractionDataOption],
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+  let id = int_option(options, "id").unwrap_or_default() as i32;
+
+  if !db.delete_react_rule(guild_id, id)? {
+    return Ok(format!("No react rule #{} in this server.", id));
+  }
+  react_store.set_guild(guild_id, &db.get_react_rules(guild_id)?);
+
+  info!(
+    "User {} removed react rule {} in guild {}",
+    command.user.id, id, guild_id
+  );
+  Ok(format!("Removed react rule #{}", id))
+}
+
+async fn list(
+  ctx: Arc<Context>,
+  db: &GotdMysqlStore,
+  command: &ApplicationCommandInteraction,
+  guild_id: u64,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+  let rules = db.get_react_rules(guild_id)?;
+  if rules.is_empty() {
+    respond(
+      &ctx,
+      command,
+      String::from("No react rules in this server yet. Add one with `/react-rule add`."),
+    )
+    .await?;
+    return Ok(());
+  }
+
+  command
+    .create_interaction_response(&ctx.http, |res| {
+      res
+        .kind(InteractionResponseType::ChannelMessageWithSource)
+        .interaction_response_data(|m| {
+          m.create_embed(|e| {
+            e.color(Colour::from(0x0099ff));
+            e.title("React rules");
+            for r in rules.iter().take(MAX_FIELDS) {
+              e.field(format!("#{}", r.id), describe(r), false);
+            }
+            if rules.len() > MAX_FIELDS {
+              e.footer(|f| f.text(format!("and {} more", rules.len() - MAX_FIELDS)));
+            }
+            e
+          })
+        })
+    })
+    .await?;
+  Ok(())
+}
+
+fn describe(rule: &ReactRule) -> String {
+  format!(
+    "{} `{}` reacts with {}",
+    rule.trigger_kind, rule.trigger_value, rule.reactions
+  )
+}
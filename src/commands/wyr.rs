@@ -1,57 +1,62 @@
+use super::{int_option, respond, string_option};
+use crate::store::model::{NewWyrPoll, NewWyrVote, WyrPoll, WyrVote, SUBMISSION_ID_PREFIX};
 use crate::store::mysql_store::GotdMysqlStore;
+use crate::store::storage::WyrDb;
+use crate::store::wyr_store::{Wyr, WyrStore};
+use chrono::{DateTime, Duration, Utc};
 use rand::Rng;
-use serde::Deserialize;
-use serde_json;
 use serenity::{
-  model::interactions::{
-    application_command::ApplicationCommandInteraction, message_component::ButtonStyle,
-    InteractionResponseType,
+  builder::{CreateComponents, CreateEmbed},
+  http::Http,
+  model::{
+    id::{ChannelId, MessageId},
+    interactions::{
+      application_command::ApplicationCommandInteraction,
+      message_component::{ButtonStyle, InteractionMessage, MessageComponentInteraction},
+      InteractionApplicationCommandCallbackDataFlags, InteractionResponseType,
+    },
   },
   prelude::Context,
+  utils::Colour,
 };
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::collections::HashSet;
 use std::sync::Arc;
-use tracing::error;
+use tracing::{error, info};
 
-#[derive(Deserialize)]
-struct Wyr {
-  id: String,
-  title: String,
-  url: String,
-  options: Vec<String>,
-}
+// approved /wyr-submit polls for the guild
+pub const COMMUNITY_COLLECTION: &str = "community";
+// what /wyr and scheduled polls draw from when no collection is given
+pub const DEFAULT_COLLECTION: &str = "top-all";
 
 pub async fn handler(
   ctx: Arc<Context>,
-  _db: &GotdMysqlStore,
+  db: &GotdMysqlStore,
+  wyr_store: &WyrStore,
   command: &ApplicationCommandInteraction,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
   let typing = command.channel_id.start_typing(&ctx.http);
-  let wyr = get_random().expect("Didn't get wyr");
+  let collection =
+    string_option(&command.data.options, "collection").unwrap_or(String::from(DEFAULT_COLLECTION));
+  let guild_id = command.guild_id.unwrap_or_default().0;
+  let wyr = match draw(db, wyr_store, guild_id, &collection)? {
+    Some(w) => w,
+    None => {
+      error!("No wyr to pick from in collection {}", collection);
+      respond(&ctx, command, String::from("Bzzzrt! Failed to find a wyr.")).await?;
+      return Ok(());
+    }
+  };
+  let counts = vec![0; wyr.options.len()];
+  let duration_mins = int_option(&command.data.options, "duration").filter(|mins| *mins > 0);
+  let closes_on = duration_mins.map(|mins| Utc::now() + Duration::minutes(mins));
 
   if let Err(why) = command
     .create_interaction_response(&ctx.http, |res| {
       res
         .kind(InteractionResponseType::ChannelMessageWithSource)
         .interaction_response_data(|m| {
-          m.create_embed(|e| {
-            e.title(&wyr.title)
-              .url(&wyr.url)
-              .description("from r/WouldYouRather")
-          })
-          .components(|c| {
-            c.create_action_row(|a| {
-              for opt in &wyr.options {
-                a.create_button(|b| {
-                  b.label(opt)
-                    .custom_id(format!("wyr::{}::{}", opt, &wyr.id))
-                    .style(ButtonStyle::Primary)
-                });
-              }
-              a
-            })
-          })
+          m.create_embed(|e| poll_embed(e, &wyr, &counts, closes_on))
+            .components(|c| poll_buttons(c, &wyr, false))
         })
     })
     .await
@@ -61,6 +66,29 @@ pub async fn handler(
       .channel_id
       .say(&ctx.http, "Bzzzrt! Failed to find a wyr.")
       .await?;
+  } else {
+    // the interaction is answered, so from here on failures are only logged
+    // the poll message only gets an id once discord has created it
+    match command.get_interaction_response(&ctx.http).await {
+      Ok(message) => {
+        if let Err(why) = db.save_poll(NewWyrPoll {
+          wyr_id: wyr.id.clone(),
+          message_id: message.id.0,
+          channel_id: command.channel_id.0,
+          guild_id,
+          closes_on_ts: closes_on.map(|ts| ts.naive_utc()),
+        }) {
+          error!("Failed to save poll for message {}: {}", message.id, why);
+        }
+      }
+      Err(why) => error!("Failed to get the poll message for wyr {}: {}", wyr.id, why),
+    }
+    if let Err(why) = db.mark_wyr_shown(guild_id, &wyr.id) {
+      error!(
+        "Failed to mark wyr {} shown in guild {}: {}",
+        wyr.id, guild_id, why
+      );
+    }
   }
 
   match typing {
@@ -73,28 +101,563 @@ pub async fn handler(
   Ok(())
 }
 
-fn get_random() -> Result<Wyr, Box<dyn std::error::Error>> {
-  let reader = BufReader::new(File::open("db/wyr/wyr-top-all.ndjson")?);
+/**
+ * Handles clicks on the buttons of a wyr poll. The custom id looks like
+ * `wyr::<option index>::<wyr id>`. Polls posted before votes were counted
+ * used the option label instead of its index, so fall back to that.
+ */
+pub async fn vote_handler(
+  ctx: Arc<Context>,
+  db: &GotdMysqlStore,
+  wyr_store: &WyrStore,
+  component: &MessageComponentInteraction,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+  let mut id_iter = component.data.custom_id.split("::").skip(1);
+  let m_opt = id_iter.next();
+  let m_wyr_id = id_iter.next();
 
-  // number between 0 and 678
-  // wyr-top-all has a 678 lines...
-  // this could be improved by collecting the number of lines, but that
-  // would require reading the entire file into memory...I think?
-  // this is good enough for now
-  let idx = random(678);
-  let line = match reader.lines().nth(idx) {
-    Some(r) => match r {
-      Ok(l) => l,
-      Err(_) => String::from(""),
-    },
-    None => String::from(""),
+  if m_opt.is_none() || m_wyr_id.is_none() {
+    return Ok(());
+  }
+
+  let opt = m_opt.unwrap();
+  let wyr_id = m_wyr_id.unwrap();
+  let userid = &component.user.id;
+  let username = &component.user.name;
+
+  info!(
+    "wyr message response {} : {} : {} : {}",
+    userid, username, opt, wyr_id
+  );
+
+  let wyr = resolve(db, wyr_store, wyr_id)?;
+  let option_idx = opt.parse::<usize>().ok().or_else(|| {
+    wyr
+      .as_ref()
+      .and_then(|w| w.options.iter().position(|o| o == opt))
+  });
+
+  let (wyr, option_idx) = match (wyr, option_idx) {
+    (Some(w), Some(idx)) if idx < w.options.len() => (w, idx),
+    _ => {
+      component
+        .create_interaction_response(&ctx.http, |res| {
+          res
+            .kind(InteractionResponseType::ChannelMessageWithSource)
+            .interaction_response_data(|m| {
+              m.flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL)
+                .content("Bzzzrt! I don't know that poll anymore.")
+            })
+        })
+        .await?;
+      return Ok(());
+    }
+  };
+
+  let message_id = match &component.message {
+    InteractionMessage::Regular(m) => m.id,
+    InteractionMessage::Ephemeral(m) => m.id,
+  };
+
+  // a click can land after the results went out, or while they're going out
+  let poll = db.get_poll(message_id.0)?;
+  if poll.as_ref().is_some_and(is_over) {
+    component
+      .create_interaction_response(&ctx.http, |res| {
+        res
+          .kind(InteractionResponseType::ChannelMessageWithSource)
+          .interaction_response_data(|m| {
+            m.flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL)
+              .content("This poll is closed, your vote wasn't counted.")
+          })
+      })
+      .await?;
+
+    let votes = db.get_votes(message_id.0)?;
+    let counts = tally(&votes, &wyr.id, wyr.options.len());
+    component
+      .channel_id
+      .edit_message(&ctx.http, message_id, |m| {
+        m.embed(|e| closed_embed(e, &wyr, &counts))
+          .components(|c| poll_buttons(c, &wyr, true))
+      })
+      .await?;
+    return Ok(());
+  }
+
+  db.save_vote(NewWyrVote {
+    wyr_id: wyr.id.clone(),
+    message_id: message_id.0,
+    channel_id: component.channel_id.0,
+    guild_id: component.guild_id.unwrap_or_default().0,
+    user_id: userid.0,
+    option_idx: option_idx as i32,
+  })?;
+
+  let votes = db.get_votes(message_id.0)?;
+  let counts = tally(&votes, &wyr.id, wyr.options.len());
+  // read the poll again, it may have closed while the vote was saved
+  let poll = db.get_poll(message_id.0)?;
+  let closed = poll.as_ref().is_some_and(is_over);
+  let closes_on = poll
+    .and_then(|p| p.closes_on_ts)
+    .map(|ts| DateTime::<Utc>::from_utc(ts, Utc));
+
+  // redraw the poll in place so everyone sees the live counts
+  component
+    .create_interaction_response(&ctx.http, |res| {
+      res
+        .kind(InteractionResponseType::UpdateMessage)
+        .interaction_response_data(|m| {
+          m.create_embed(|e| match closed {
+            true => closed_embed(e, &wyr, &counts),
+            false => poll_embed(e, &wyr, &counts, closes_on),
+          })
+          .components(|c| poll_buttons(c, &wyr, closed))
+        })
+    })
+    .await?;
+
+  Ok(())
+}
+
+/**
+ * Close a poll whose time is up: redraw it with the buttons disabled and
+ * announce the results in the channel.
+ */
+pub async fn close_poll(
+  http: &Arc<Http>,
+  db: Arc<GotdMysqlStore>,
+  wyr_store: &WyrStore,
+  poll: &WyrPoll,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+  // close it first so a failure below can't make us announce it twice
+  if !db.close_poll(poll.id)? {
+    return Ok(());
+  }
+
+  let wyr = match resolve(&db, wyr_store, &poll.wyr_id)? {
+    Some(w) => w,
+    None => {
+      error!("Closed poll {} for unknown wyr {}", poll.id, poll.wyr_id);
+      return Ok(());
+    }
+  };
+
+  let votes = db.get_votes(poll.message_id)?;
+  let counts = tally(&votes, &wyr.id, wyr.options.len());
+  let channel = ChannelId(poll.channel_id);
+
+  channel
+    .edit_message(http, MessageId(poll.message_id), |m| {
+      m.embed(|e| closed_embed(e, &wyr, &counts))
+        .components(|c| poll_buttons(c, &wyr, true))
+    })
+    .await?;
+
+  channel
+    .send_message(http, |m| {
+      m.reference_message((channel, MessageId(poll.message_id)))
+        .embed(|e| results_embed(e, &wyr, &votes, &counts))
+    })
+    .await?;
+
+  info!("Closed wyr poll {} in channel {}", poll.id, channel);
+  Ok(())
+}
+
+/**
+ * Post a poll from the default collection to a channel on a schedule. Works
+ * like `/wyr` without a duration, so the poll stays open.
+ */
+pub async fn send_wyr(
+  http: &Arc<Http>,
+  db: Arc<GotdMysqlStore>,
+  wyr_store: &WyrStore,
+  channel_id: u64,
+  guild_id: u64,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+  let wyr = match draw(&db, wyr_store, guild_id, DEFAULT_COLLECTION)? {
+    Some(w) => w,
+    None => return Err(format!("No wyr to pick from for guild {}", guild_id).into()),
+  };
+  let counts = vec![0; wyr.options.len()];
+  let channel = ChannelId(channel_id);
+
+  let message = channel
+    .send_message(http, |m| {
+      m.embed(|e| poll_embed(e, &wyr, &counts, None))
+        .components(|c| poll_buttons(c, &wyr, false))
+    })
+    .await?;
+
+  db.save_poll(NewWyrPoll {
+    wyr_id: wyr.id.clone(),
+    message_id: message.id.0,
+    channel_id,
+    guild_id,
+    closes_on_ts: None,
+  })?;
+  db.mark_wyr_shown(guild_id, &wyr.id)?;
+
+  info!("Sent scheduled wyr {} to channel {}", wyr.id, channel_id);
+  Ok(())
+}
+
+/**
+ * Pick a wyr for the guild that it hasn't seen yet. Once every wyr in the
+ * pool has been shown, forget them and start over, without repeating the
+ * one shown last.
+ */
+fn draw(
+  db: &GotdMysqlStore,
+  wyr_store: &WyrStore,
+  guild_id: u64,
+  collection: &str,
+) -> Result<Option<Arc<Wyr>>, Box<dyn std::error::Error + Send + Sync>> {
+  let pool = draw_pool(db, wyr_store, guild_id, collection)?;
+  let shown_ids = db.get_shown_wyr(guild_id)?;
+  let last = shown_ids.last().cloned();
+  let shown = shown_ids.into_iter().collect::<HashSet<String>>();
+
+  let fresh = unseen(&pool, &shown);
+  if !fresh.is_empty() {
+    return Ok(pick(&fresh));
+  }
+
+  if !pool.is_empty() {
+    info!(
+      "Guild {} has seen all {} wyr in {}, starting over",
+      guild_id,
+      pool.len(),
+      collection
+    );
+    let ids = pool.iter().map(|w| w.id.clone()).collect::<Vec<String>>();
+    db.reset_shown_wyr(guild_id, &ids)?;
+  }
+  Ok(pick(&without_last(&pool, last.as_deref())))
+}
+
+/**
+ * The pool minus the wyr shown last, unless that's all there is
+ */
+fn without_last(pool: &[Arc<Wyr>], last: Option<&str>) -> Vec<Arc<Wyr>> {
+  let rest = pool
+    .iter()
+    .filter(|w| Some(w.id.as_str()) != last)
+    .cloned()
+    .collect::<Vec<Arc<Wyr>>>();
+  match rest.is_empty() {
+    true => pool.to_vec(),
+    false => rest,
+  }
+}
+
+fn unseen(pool: &[Arc<Wyr>], shown: &HashSet<String>) -> Vec<Arc<Wyr>> {
+  pool
+    .iter()
+    .filter(|w| !shown.contains(&w.id))
+    .cloned()
+    .collect()
+}
+
+/**
+ * Every wyr `/wyr` can draw from for the collection. Approved submissions
+ * from the guild are their own "community" collection and are part of "any".
+ */
+fn draw_pool(
+  db: &GotdMysqlStore,
+  wyr_store: &WyrStore,
+  guild_id: u64,
+  collection: &str,
+) -> Result<Vec<Arc<Wyr>>, Box<dyn std::error::Error + Send + Sync>> {
+  let mut pool = match collection {
+    COMMUNITY_COLLECTION => vec![],
+    c => wyr_store.pool(c),
+  };
+
+  if collection == COMMUNITY_COLLECTION || collection == "any" {
+    pool.extend(
+      db.get_approved_submissions(guild_id)?
+        .iter()
+        .filter_map(|s| s.to_wyr())
+        .map(Arc::new),
+    );
+  }
+
+  Ok(pool)
+}
+
+fn pick(pool: &[Arc<Wyr>]) -> Option<Arc<Wyr>> {
+  if pool.is_empty() {
+    return None;
+  }
+
+  let idx = rand::thread_rng().gen_range(0..pool.len());
+  Some(Arc::clone(&pool[idx]))
+}
+
+/**
+ * Find a wyr by id, whether it came from the datasets or a submission
+ */
+fn resolve(
+  db: &GotdMysqlStore,
+  wyr_store: &WyrStore,
+  wyr_id: &str,
+) -> Result<Option<Arc<Wyr>>, Box<dyn std::error::Error + Send + Sync>> {
+  match wyr_id
+    .strip_prefix(SUBMISSION_ID_PREFIX)
+    .and_then(|sub| sub.parse::<i32>().ok())
+  {
+    Some(sub) => Ok(
+      db.get_submission(sub)?
+        .and_then(|s| s.to_wyr())
+        .map(Arc::new),
+    ),
+    None => Ok(wyr_store.get(wyr_id)),
+  }
+}
+
+fn poll_embed<'a>(
+  e: &'a mut CreateEmbed,
+  wyr: &Wyr,
+  counts: &[usize],
+  closes_on: Option<DateTime<Utc>>,
+) -> &'a mut CreateEmbed {
+  let total = counts.iter().sum::<usize>();
+  e.title(&wyr.title);
+  match wyr.submitted_by {
+    Some(user) => e.description(format!("submitted by <@{}>", user)),
+    None => e.url(&wyr.url).description("from r/WouldYouRather"),
+  };
+  for (opt, count) in wyr.options.iter().zip(counts) {
+    e.field(opt, tally_line(*count, total), false);
+  }
+  match closes_on {
+    // discord renders the timestamp in each reader's own timezone
+    Some(ts) => {
+      e.footer(|f| f.text(format!("{} vote(s) - closes", total)));
+      e.timestamp(&ts)
+    }
+    None => e.footer(|f| f.text(format!("{} vote(s)", total))),
+  }
+}
+
+/**
+ * Whether the poll takes no more votes, either closed already or past its
+ * closing time and waiting on the scheduler to announce it
+ */
+fn is_over(poll: &WyrPoll) -> bool {
+  poll.is_closed || poll.is_due(Utc::now().naive_utc())
+}
+
+fn closed_embed<'a>(e: &'a mut CreateEmbed, wyr: &Wyr, counts: &[usize]) -> &'a mut CreateEmbed {
+  poll_embed(e, wyr, counts, None);
+  e.footer(|f| f.text(format!("{} vote(s) - closed", counts.iter().sum::<usize>())))
+}
+
+fn poll_buttons<'a>(
+  c: &'a mut CreateComponents,
+  wyr: &Wyr,
+  disabled: bool,
+) -> &'a mut CreateComponents {
+  c.create_action_row(|a| {
+    for (idx, opt) in wyr.options.iter().enumerate() {
+      a.create_button(|b| {
+        b.label(opt)
+          .custom_id(format!("wyr::{}::{}", idx, &wyr.id))
+          .style(ButtonStyle::Primary)
+          .disabled(disabled)
+      });
+    }
+    a
+  })
+}
+
+fn results_embed<'a>(
+  e: &'a mut CreateEmbed,
+  wyr: &Wyr,
+  votes: &[WyrVote],
+  counts: &[usize],
+) -> &'a mut CreateEmbed {
+  let total = counts.iter().sum::<usize>();
+  let winning = winners(counts);
+  let headline = match winning.as_slice() {
+    [] => String::from("Nobody voted :("),
+    [w] => format!("**{}** wins!", wyr.options[*w]),
+    many => format!(
+      "It's a tie between {}",
+      many
+        .iter()
+        .map(|w| format!("**{}**", wyr.options[*w]))
+        .collect::<Vec<String>>()
+        .join(" and ")
+    ),
   };
 
-  let wyr: Wyr = serde_json::from_str(&line)?;
-  Ok(wyr)
+  e.color(Colour::from(0x0099ff));
+  e.title(format!("Results: {}", wyr.title));
+  e.description(headline);
+  for (idx, opt) in wyr.options.iter().enumerate() {
+    let voters = votes
+      .iter()
+      .filter(|v| v.wyr_id == wyr.id && v.option_idx as usize == idx)
+      .map(|v| format!("<@{}>", v.user_id))
+      .collect::<Vec<String>>();
+    e.field(
+      format!("{} - {}", opt, tally_line(counts[idx], total)),
+      truncate_voters(&voters),
+      false,
+    );
+  }
+  e
 }
 
-fn random(max: usize) -> usize {
-  // get random int between 0 and (max - 1)
-  rand::thread_rng().gen_range(0..max)
+/**
+ * The indexes of the option(s) with the most votes. Empty if nobody voted.
+ */
+fn winners(counts: &[usize]) -> Vec<usize> {
+  let max = counts.iter().copied().max().unwrap_or(0);
+  if max == 0 {
+    return vec![];
+  }
+  counts
+    .iter()
+    .enumerate()
+    .filter(|(_, c)| **c == max)
+    .map(|(idx, _)| idx)
+    .collect()
+}
+
+// embed field values are capped at 1024 characters
+fn truncate_voters(voters: &[String]) -> String {
+  if voters.is_empty() {
+    return String::from("no votes");
+  }
+
+  let mut out = String::new();
+  for (idx, voter) in voters.iter().enumerate() {
+    let more = format!(" and {} more", voters.len() - idx);
+    if out.len() + voter.len() + 2 + more.len() > 1024 {
+      out.push_str(&more);
+      break;
+    }
+    if !out.is_empty() {
+      out.push_str(", ");
+    }
+    out.push_str(voter);
+  }
+  out
+}
+
+/**
+ * Count the votes for each option of the poll
+ */
+fn tally(votes: &[WyrVote], wyr_id: &str, num_options: usize) -> Vec<usize> {
+  let mut counts = vec![0; num_options];
+  for vote in votes.iter().filter(|v| v.wyr_id == wyr_id) {
+    if let Some(c) = counts.get_mut(vote.option_idx as usize) {
+      *c += 1;
+    }
+  }
+  counts
+}
+
+fn tally_line(count: usize, total: usize) -> String {
+  let pct = if total == 0 {
+    0.0
+  } else {
+    count as f64 * 100.0 / total as f64
+  };
+  let filled = (pct / 10.0).round() as usize;
+  format!(
+    "`{}{}` {} ({:.0}%)",
+    "█".repeat(filled),
+    "░".repeat(10 - filled),
+    count,
+    pct
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn vote(wyr_id: &str, user_id: u64, option_idx: i32) -> WyrVote {
+    WyrVote {
+      id: 1,
+      wyr_id: String::from(wyr_id),
+      message_id: 1,
+      channel_id: 1,
+      guild_id: 1,
+      user_id,
+      option_idx,
+      voted_on_ts: chrono::NaiveDateTime::from_timestamp(0, 0),
+    }
+  }
+
+  #[test]
+  fn tally_counts_votes_per_option() {
+    let votes = vec![
+      vote("a", 1, 0),
+      vote("a", 2, 1),
+      vote("a", 3, 1),
+      vote("b", 4, 0),
+    ];
+    assert_eq!(tally(&votes, "a", 3), vec![1, 2, 0]);
+  }
+
+  #[test]
+  fn tally_ignores_out_of_range_options() {
+    let votes = vec![vote("a", 1, 7), vote("a", 2, -1)];
+    assert_eq!(tally(&votes, "a", 2), vec![0, 0]);
+  }
+
+  #[test]
+  fn tally_line_shows_count_and_percentage() {
+    assert_eq!(tally_line(3, 4), "`████████░░` 3 (75%)");
+    assert_eq!(tally_line(0, 0), "`░░░░░░░░░░` 0 (0%)");
+  }
+
+  #[test]
+  fn winners_finds_single_winner_and_ties() {
+    assert_eq!(winners(&[1, 3, 2]), vec![1]);
+    assert_eq!(winners(&[2, 0, 2]), vec![0, 2]);
+    assert_eq!(winners(&[0, 0]), Vec::<usize>::new());
+  }
+
+  fn wyr(id: &str) -> Arc<Wyr> {
+    Arc::new(Wyr {
+      id: String::from(id),
+      title: String::from("WYR?"),
+      url: String::new(),
+      options: vec![String::from("a"), String::from("b")],
+      submitted_by: None,
+    })
+  }
+
+  #[test]
+  fn unseen_leaves_out_shown_wyr() {
+    let pool = vec![wyr("a"), wyr("b"), wyr("c")];
+    let shown = vec![String::from("b"), String::from("z")]
+      .into_iter()
+      .collect::<HashSet<String>>();
+
+    let fresh = unseen(&pool, &shown);
+    assert_eq!(
+      fresh.iter().map(|w| w.id.as_str()).collect::<Vec<&str>>(),
+      vec!["a", "c"]
+    );
+  }
+
+  #[test]
+  fn without_last_skips_the_wyr_shown_last() {
+    let pool = vec![wyr("a"), wyr("b")];
+    let ids = |ws: Vec<Arc<Wyr>>| ws.iter().map(|w| w.id.clone()).collect::<Vec<String>>();
+
+    assert_eq!(ids(without_last(&pool, Some("a"))), vec!["b"]);
+    assert_eq!(ids(without_last(&pool, None)), vec!["a", "b"]);
+    assert_eq!(ids(without_last(&pool, Some("z"))), vec!["a", "b"]);
+    assert_eq!(ids(without_last(&pool[..1], Some("a"))), vec!["a"]);
+  }
 }
@@ -0,0 +1,91 @@
+use crate::store::wyr_store::WyrStore;
+use serenity::{
+  model::interactions::{
+    application_command::ApplicationCommandInteraction,
+    InteractionApplicationCommandCallbackDataFlags, InteractionResponseType,
+  },
+  prelude::Context,
+};
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+// keep the reply well under discord's 2000 character limit
+const MAX_LISTED_IDS: usize = 10;
+
+pub async fn handler(
+  ctx: Arc<Context>,
+  wyr_store: &WyrStore,
+  command: &ApplicationCommandInteraction,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+  // only whoever owns the bot application gets to touch the datasets
+  let app = ctx.http.get_current_application_info().await?;
+  let is_owner = app.owner.id == command.user.id
+    || app
+      .team
+      .map(|t| t.members.iter().any(|m| m.user.id == command.user.id))
+      .unwrap_or(false);
+
+  let msg = if !is_owner {
+    warn!(
+      "User {} tried to reload wyr without owning the bot",
+      command.user.id
+    );
+    String::from("Only the bot owner can reload polls.")
+  } else {
+    match wyr_store.reload() {
+      Ok(summary) => {
+        info!("User {} reloaded wyr", command.user.id);
+        let mut msg = format!(
+          "Reloaded {} polls: {} added{}, {} removed{}.",
+          summary.total,
+          summary.added.len(),
+          list_ids(&summary.added),
+          summary.removed.len(),
+          list_ids(&summary.removed)
+        );
+        if let Some(first) = summary.skipped.first() {
+          msg.push_str(&format!(
+            " Skipped {} line(s) that can't be polls, the logs list them all. First: {}",
+            summary.skipped.len(),
+            first
+          ));
+        }
+        msg
+      }
+      Err(why) => {
+        error!("Failed to reload wyr: {}", why);
+        format!("Bzzzrt! Reload failed, still using the old polls. {}", why)
+      }
+    }
+  };
+
+  command
+    .create_interaction_response(&ctx.http, |res| {
+      res
+        .kind(InteractionResponseType::ChannelMessageWithSource)
+        .interaction_response_data(|m| {
+          m.flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL)
+            .content(msg)
+        })
+    })
+    .await?;
+
+  Ok(())
+}
+
+fn list_ids(ids: &[String]) -> String {
+  if ids.is_empty() {
+    return String::new();
+  }
+
+  let mut listed = ids
+    .iter()
+    .take(MAX_LISTED_IDS)
+    .map(|id| format!("`{}`", id))
+    .collect::<Vec<String>>()
+    .join(", ");
+  if ids.len() > MAX_LISTED_IDS {
+    listed.push_str(&format!(" and {} more", ids.len() - MAX_LISTED_IDS));
+  }
+  format!(" ({})", listed)
+}
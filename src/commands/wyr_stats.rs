@@ -0,0 +1,274 @@
+use super::super::GotdMysqlStore;
+use super::option;
+use crate::store::model::WyrVote;
+use crate::store::storage::WyrDb;
+use serenity::{
+  model::{
+    id::UserId,
+    interactions::{
+      application_command::{
+        ApplicationCommandInteraction, ApplicationCommandInteractionDataOptionValue,
+      },
+      InteractionResponseType,
+    },
+  },
+  prelude::Context,
+  utils::Colour,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::info;
+
+// how many members each leaderboard shows
+const LEADERBOARD_SIZE: usize = 5;
+// a couple of lucky votes shouldn't top the contrarian board
+const MIN_DECIDED_VOTES: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct UserStats {
+  pub user_id: u64,
+  pub votes: usize,
+  // votes on polls where one option had more votes than every other
+  pub decided: usize,
+  pub with_majority: usize,
+}
+
+impl UserStats {
+  /**
+   * How often the user sided with the majority, from 0 to 1. None until
+   * they have voted on a poll that had a majority.
+   */
+  pub fn majority_rate(&self) -> Option<f64> {
+    if self.decided == 0 {
+      return None;
+    }
+    Some(self.with_majority as f64 / self.decided as f64)
+  }
+}
+
+pub async fn handler(
+  ctx: Arc<Context>,
+  db: &GotdMysqlStore,
+  command: &ApplicationCommandInteraction,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+  let user_id = match option(&command.data.options, "user") {
+    Some(ApplicationCommandInteractionDataOptionValue::User(u, _)) => u.id,
+    _ => command.user.id,
+  };
+  let guild_id = command.guild_id.unwrap_or_default().0;
+
+  let votes = db.get_guild_votes(guild_id)?;
+  let stats = compute_stats(&votes);
+  info!(
+    "User {} asked for wyr stats of {} in guild {} ({} votes)",
+    command.user.id,
+    user_id,
+    guild_id,
+    votes.len()
+  );
+
+  let mine = stats.get(&user_id.0).cloned().unwrap_or(UserStats {
+    user_id: user_id.0,
+    ..UserStats::default()
+  });
+  let active = leaderboard_lines(&most_active(&stats), |s| format!("{} vote(s)", s.votes));
+  let contrarian = leaderboard_lines(&most_contrarian(&stats), |s| {
+    format!(
+      "against the majority {} of {} time(s)",
+      s.decided - s.with_majority,
+      s.decided
+    )
+  });
+
+  command
+    .create_interaction_response(&ctx.http, |res| {
+      res
+        .kind(InteractionResponseType::ChannelMessageWithSource)
+        .interaction_response_data(|m| {
+          m.create_embed(|e| {
+            e.color(Colour::from(0x0099ff));
+            e.title("Would You Rather stats");
+            e.field("Voter", describe_user(user_id, &mine), false);
+            e.field("Most active", active, false);
+            e.field("Most contrarian", contrarian, false)
+          })
+        })
+    })
+    .await?;
+
+  Ok(())
+}
+
+/**
+ * Work out every voter's stats from the votes cast in a guild. Each poll
+ * message is its own poll, even when the same wyr was posted twice.
+ */
+pub fn compute_stats(votes: &[WyrVote]) -> HashMap<u64, UserStats> {
+  let mut polls = HashMap::<u64, HashMap<i32, usize>>::new();
+  for v in votes {
+    *polls
+      .entry(v.message_id)
+      .or_default()
+      .entry(v.option_idx)
+      .or_default() += 1;
+  }
+  let majorities = polls
+    .iter()
+    .filter_map(|(message_id, counts)| majority(counts).map(|idx| (*message_id, idx)))
+    .collect::<HashMap<u64, i32>>();
+
+  let mut stats = HashMap::<u64, UserStats>::new();
+  for v in votes {
+    let s = stats.entry(v.user_id).or_insert(UserStats {
+      user_id: v.user_id,
+      ..UserStats::default()
+    });
+    s.votes += 1;
+    if let Some(idx) = majorities.get(&v.message_id) {
+      s.decided += 1;
+      if *idx == v.option_idx {
+        s.with_majority += 1;
+      }
+    }
+  }
+  stats
+}
+
+/**
+ * The option with more votes than any other. Ties have no majority.
+ */
+fn majority(counts: &HashMap<i32, usize>) -> Option<i32> {
+  let top = counts.values().max()?;
+  let mut leaders = counts.iter().filter(|(_, c)| *c == top);
+  let (idx, _) = leaders.next()?;
+  match leaders.next() {
+    Some(_) => None,
+    None => Some(*idx),
+  }
+}
+
+/**
+ * Voters with the most votes, ties broken by user id so the board is stable
+ */
+pub fn most_active(stats: &HashMap<u64, UserStats>) -> Vec<UserStats> {
+  let mut board = stats.values().cloned().collect::<Vec<UserStats>>();
+  board.sort_by(|a, b| b.votes.cmp(&a.votes).then(a.user_id.cmp(&b.user_id)));
+  board.truncate(LEADERBOARD_SIZE);
+  board
+}
+
+/**
+ * Voters who side with the majority least often, among those with enough
+ * decided votes for it to mean something
+ */
+pub fn most_contrarian(stats: &HashMap<u64, UserStats>) -> Vec<UserStats> {
+  let mut board = stats
+    .values()
+    .filter(|s| s.decided >= MIN_DECIDED_VOTES)
+    .cloned()
+    .collect::<Vec<UserStats>>();
+  board.sort_by(|a, b| {
+    let a_rate = a.majority_rate().unwrap_or(1.0);
+    let b_rate = b.majority_rate().unwrap_or(1.0);
+    a_rate
+      .partial_cmp(&b_rate)
+      .unwrap_or(std::cmp::Ordering::Equal)
+      .then(b.decided.cmp(&a.decided))
+      .then(a.user_id.cmp(&b.user_id))
+  });
+  board.truncate(LEADERBOARD_SIZE);
+  board
+}
+
+fn describe_user(user_id: UserId, s: &UserStats) -> String {
+  match s.majority_rate() {
+    Some(rate) => format!(
+      "<@{}> voted {} time(s) and sided with the majority {} of {} ({:.0}%)",
+      user_id,
+      s.votes,
+      s.with_majority,
+      s.decided,
+      rate * 100.0
+    ),
+    None if s.votes > 0 => format!(
+      "<@{}> voted {} time(s), none on a poll with a clear majority yet",
+      user_id, s.votes
+    ),
+    None => format!("<@{}> hasn't voted on any polls yet", user_id),
+  }
+}
+
+fn leaderboard_lines<F>(board: &[UserStats], detail: F) -> String
+where
+  F: Fn(&UserStats) -> String,
+{
+  if board.is_empty() {
+    return String::from("Nobody yet");
+  }
+  board
+    .iter()
+    .enumerate()
+    .map(|(i, s)| format!("{}. <@{}> {}", i + 1, s.user_id, detail(s)))
+    .collect::<Vec<String>>()
+    .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn vote(message_id: u64, user_id: u64, option_idx: i32) -> WyrVote {
+    WyrVote {
+      id: 0,
+      wyr_id: String::from("w"),
+      message_id,
+      channel_id: 1,
+      guild_id: 1,
+      user_id,
+      option_idx,
+      voted_on_ts: chrono::NaiveDateTime::from_timestamp(0, 0),
+    }
+  }
+
+  #[test]
+  fn compute_stats_counts_majority() {
+    let votes = vec![
+      vote(1, 10, 0),
+      vote(1, 11, 0),
+      vote(1, 12, 1),
+      // tied, nobody is in the majority
+      vote(2, 10, 0),
+      vote(2, 12, 1),
+    ];
+    let stats = compute_stats(&votes);
+
+    assert_eq!(stats[&10].votes, 2);
+    assert_eq!(stats[&10].decided, 1);
+    assert_eq!(stats[&10].with_majority, 1);
+    assert_eq!(stats[&12].with_majority, 0);
+    assert_eq!(stats[&12].majority_rate(), Some(0.0));
+  }
+
+  #[test]
+  fn most_contrarian_needs_enough_votes() {
+    let mut votes = vec![];
+    for m in 1..=3 {
+      votes.push(vote(m, 10, 0));
+      votes.push(vote(m, 11, 0));
+      votes.push(vote(m, 12, 1));
+    }
+    // one lone dissent isn't enough to make the board
+    votes.push(vote(4, 10, 0));
+    votes.push(vote(4, 11, 0));
+    votes.push(vote(4, 13, 1));
+    let stats = compute_stats(&votes);
+
+    let board = most_contrarian(&stats);
+    assert_eq!(board[0].user_id, 12);
+    assert!(board.iter().all(|s| s.user_id != 13));
+
+    let active = most_active(&stats);
+    assert_eq!(active[0].user_id, 10);
+    assert_eq!(active[0].votes, 4);
+  }
+}
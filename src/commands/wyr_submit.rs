@@ -0,0 +1,223 @@
+use super::super::GotdMysqlStore;
+use super::{respond, string_option};
+use crate::store::model::{
+  NewWyrSubmission, WyrSubmission, SUBMISSION_APPROVED, SUBMISSION_PENDING, SUBMISSION_REJECTED,
+};
+use crate::store::storage::WyrDb;
+use crate::store::wyr_store::{validate, Wyr};
+use serenity::{
+  builder::{CreateComponents, CreateEmbed},
+  model::interactions::{
+    application_command::ApplicationCommandInteraction,
+    message_component::{ButtonStyle, MessageComponentInteraction},
+    InteractionApplicationCommandCallbackDataFlags, InteractionResponseType,
+  },
+  prelude::Context,
+  utils::Colour,
+};
+use std::sync::Arc;
+use tracing::{error, info};
+
+// the title column is a VARCHAR(255)
+const MAX_TITLE_LEN: usize = 255;
+
+pub async fn handler(
+  ctx: Arc<Context>,
+  db: &GotdMysqlStore,
+  command: &ApplicationCommandInteraction,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+  let guild_id = match command.guild_id {
+    Some(g) => g,
+    None => {
+      respond(
+        &ctx,
+        command,
+        String::from("Polls can only be submitted in a server."),
+      )
+      .await?;
+      return Ok(());
+    }
+  };
+
+  let title = string_option(&command.data.options, "title").unwrap_or_default();
+  let options = ["option1", "option2", "option3", "option4", "option5"]
+    .iter()
+    .filter_map(|name| string_option(&command.data.options, name))
+    .map(|o| String::from(o.trim()))
+    .collect::<Vec<String>>();
+
+  let candidate = Wyr {
+    id: String::from("pending"),
+    title: String::from(title.trim()),
+    url: String::new(),
+    options,
+    submitted_by: Some(command.user.id.0),
+  };
+  let checked = validate(&candidate).and_then(|_| {
+    if candidate.title.chars().count() > MAX_TITLE_LEN {
+      Err(format!(
+        "title must be at most {} characters",
+        MAX_TITLE_LEN
+      ))
+    } else {
+      Ok(())
+    }
+  });
+  if let Err(why) = checked {
+    respond(&ctx, command, format!("Can't submit that poll: {}", why)).await?;
+    return Ok(());
+  }
+
+  let submission = db.save_submission(NewWyrSubmission {
+    guild_id: guild_id.0,
+    channel_id: command.channel_id.0,
+    submitted_by_id: command.user.id.0,
+    title: candidate.title.clone(),
+    options: serde_json::to_string(&candidate.options)?,
+  })?;
+
+  info!(
+    "User {} submitted wyr {} in guild {}",
+    command.user.id, submission.id, guild_id
+  );
+
+  command
+    .create_interaction_response(&ctx.http, |res| {
+      res
+        .kind(InteractionResponseType::ChannelMessageWithSource)
+        .interaction_response_data(|m| {
+          m.create_embed(|e| submission_embed(e, &submission))
+            .components(|c| review_buttons(c, submission.id, false))
+        })
+    })
+    .await?;
+
+  Ok(())
+}
+
+/**
+ * Handles the approve/reject buttons. The custom id looks like
+ * `wyr-submit::<approve|reject>::<submission id>`
+ */
+pub async fn review_handler(
+  ctx: Arc<Context>,
+  db: &GotdMysqlStore,
+  component: &MessageComponentInteraction,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+  let mut id_iter = component.data.custom_id.split("::").skip(1);
+  let status = match id_iter.next() {
+    Some("approve") => SUBMISSION_APPROVED,
+    Some("reject") => SUBMISSION_REJECTED,
+    _ => return Ok(()),
+  };
+  let submission_id = match id_iter.next().and_then(|i| i.parse::<i32>().ok()) {
+    Some(i) => i,
+    None => return Ok(()),
+  };
+
+  let is_moderator = component
+    .member
+    .as_ref()
+    .and_then(|m| m.permissions)
+    .map(|p| p.manage_messages())
+    .unwrap_or(false);
+  if !is_moderator {
+    return reply_ephemeral(&ctx, component, "Only moderators can review submissions.").await;
+  }
+
+  if !db.review_submission(submission_id, status, component.user.id.0)? {
+    return reply_ephemeral(&ctx, component, "That submission was already reviewed.").await;
+  }
+
+  info!(
+    "User {} marked wyr submission {} {}",
+    component.user.id, submission_id, status
+  );
+
+  let submission = match db.get_submission(submission_id)? {
+    Some(s) => s,
+    None => {
+      error!("Reviewed wyr submission {} disappeared", submission_id);
+      return Ok(());
+    }
+  };
+
+  component
+    .create_interaction_response(&ctx.http, |res| {
+      res
+        .kind(InteractionResponseType::UpdateMessage)
+        .interaction_response_data(|m| {
+          m.create_embed(|e| submission_embed(e, &submission))
+            .components(|c| review_buttons(c, submission.id, true))
+        })
+    })
+    .await?;
+
+  Ok(())
+}
+
+async fn reply_ephemeral(
+  ctx: &Arc<Context>,
+  component: &MessageComponentInteraction,
+  msg: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+  component
+    .create_interaction_response(&ctx.http, |res| {
+      res
+        .kind(InteractionResponseType::ChannelMessageWithSource)
+        .interaction_response_data(|m| {
+          m.flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL)
+            .content(msg)
+        })
+    })
+    .await?;
+  Ok(())
+}
+
+fn submission_embed<'a>(e: &'a mut CreateEmbed, submission: &WyrSubmission) -> &'a mut CreateEmbed {
+  let options = serde_json::from_str::<Vec<String>>(&submission.options).unwrap_or_default();
+  let status = match (submission.status.as_str(), submission.reviewed_by_id) {
+    (SUBMISSION_PENDING, _) => String::from("Waiting for a moderator"),
+    (s, Some(reviewer)) => format!("{} by <@{}>", s, reviewer),
+    (s, None) => String::from(s),
+  };
+
+  e.color(Colour::from(0x0099ff));
+  e.author(|a| a.name(format!("Would You Rather submission #{}", submission.id)));
+  e.title(&submission.title);
+  e.description(
+    options
+      .iter()
+      .map(|o| format!("- {}", o))
+      .collect::<Vec<String>>()
+      .join("\n"),
+  );
+  e.field(
+    "submitted by",
+    format!("<@{}>", submission.submitted_by_id),
+    true,
+  );
+  e.field("status", status, true);
+  e
+}
+
+fn review_buttons(
+  c: &mut CreateComponents,
+  submission_id: i32,
+  disabled: bool,
+) -> &mut CreateComponents {
+  c.create_action_row(|a| {
+    a.create_button(|b| {
+      b.label("Approve")
+        .custom_id(format!("wyr-submit::approve::{}", submission_id))
+        .style(ButtonStyle::Success)
+        .disabled(disabled)
+    });
+    a.create_button(|b| {
+      b.label("Reject")
+        .custom_id(format!("wyr-submit::reject::{}", submission_id))
+        .style(ButtonStyle::Danger)
+        .disabled(disabled)
+    })
+  })
+}
@@ -0,0 +1,356 @@
+use crate::store::model::LEGACY_TIMEZONE;
+use chrono_tz::Tz;
+use serde::Deserialize;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/**
+ * File read when `ANDREWBOT_CONFIG` doesn't point somewhere else. It's fine
+ * for it not to exist, everything can come from the environment.
+ */
+pub const DEFAULT_CONFIG_PATH: &str = "config.toml";
+
+#[derive(Debug, Clone)]
+pub struct Config {
+  pub database_url: String,
+  pub discord_token: String,
+  pub application_id: u64,
+  pub command_prefix: String,
+  // where to fetch a random Game of the Day from without a giantbomb key
+  pub gotd_api_url: String,
+  // with a key games come straight from the giantbomb api
+  pub giantbomb_api_key: Option<String>,
+  pub giantbomb_base_url: String,
+  // each request gives up after this long, and is tried again this many times
+  pub giantbomb_timeout_secs: u64,
+  pub giantbomb_retries: u32,
+  // serve games from json files instead of the network, for working offline
+  pub game_fixtures_dir: Option<PathBuf>,
+  // keep fetching games into the cache in the background until it has this many
+  pub game_cache_target: i64,
+  pub game_cache_warm_secs: u64,
+  // timezone for new schedules that don't pick one
+  pub default_timezone: String,
+  pub wyr_dir: PathBuf,
+  pub ops_channel_id: Option<u64>,
+  pub giantbomb_failure_alert: u32,
+  pub catch_up_grace_mins: i64,
+  pub no_repeat_days: i64,
+  pub react: ReactConfig,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReactConfig {
+  pub channel_cooldown_secs: u64,
+  pub rule_cooldown_secs: u64,
+  pub burst: u32,
+  pub refill_secs: u64,
+}
+
+/**
+ * Every problem found while loading, so they can all be fixed in one go
+ */
+#[derive(Debug)]
+pub struct ConfigError {
+  pub problems: Vec<String>,
+}
+
+impl fmt::Display for ConfigError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    writeln!(f, "{} config problem(s):", self.problems.len())?;
+    for p in self.problems.iter() {
+      writeln!(f, "  - {}", p)?;
+    }
+    Ok(())
+  }
+}
+
+impl std::error::Error for ConfigError {}
+
+/**
+ * The config file as written. Everything is optional here, the env can
+ * fill in or override any of it.
+ */
+#[derive(Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+struct RawConfig {
+  database_url: Option<String>,
+  discord_token: Option<String>,
+  application_id: Option<u64>,
+  command_prefix: Option<String>,
+  gotd_api_url: Option<String>,
+  giantbomb_api_key: Option<String>,
+  giantbomb_base_url: Option<String>,
+  giantbomb_timeout_secs: Option<u64>,
+  giantbomb_retries: Option<u32>,
+  game_fixtures_dir: Option<String>,
+  game_cache_target: Option<i64>,
+  game_cache_warm_secs: Option<u64>,
+  default_timezone: Option<String>,
+  wyr_dir: Option<String>,
+  ops_channel_id: Option<u64>,
+  giantbomb_failure_alert: Option<u32>,
+  catch_up_grace_mins: Option<i64>,
+  no_repeat_days: Option<i64>,
+  #[serde(default)]
+  react: RawReactConfig,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+struct RawReactConfig {
+  channel_cooldown_secs: Option<u64>,
+  rule_cooldown_secs: Option<u64>,
+  burst: Option<u32>,
+  refill_secs: Option<u64>,
+}
+
+impl Config {
+  /**
+   * Load the config file named by `ANDREWBOT_CONFIG` (or config.toml) and
+   * apply env overrides on top
+   */
+  pub fn load() -> Result<Self, ConfigError> {
+    let path = std::env::var("ANDREWBOT_CONFIG").unwrap_or(String::from(DEFAULT_CONFIG_PATH));
+    let contents = match fs::read_to_string(Path::new(&path)) {
+      Ok(c) => c,
+      Err(why) if why.kind() == std::io::ErrorKind::NotFound => String::new(),
+      Err(why) => {
+        return Err(ConfigError {
+          problems: vec![format!("couldn't read {}: {}", path, why)],
+        })
+      }
+    };
+
+    Self::from_sources(&contents, |name| std::env::var(name).ok())
+  }
+
+  /**
+   * Build the config from toml and a way to look up env vars
+   */
+  pub fn from_sources<F>(toml_str: &str, env: F) -> Result<Self, ConfigError>
+  where
+    F: Fn(&str) -> Option<String>,
+  {
+    let mut problems = Vec::<String>::new();
+    let mut raw = match toml::from_str::<RawConfig>(toml_str) {
+      Ok(r) => r,
+      Err(why) => {
+        problems.push(format!("config file isn't valid: {}", why));
+        RawConfig::default()
+      }
+    };
+
+    let mut o = Overrides {
+      env: &env,
+      problems: &mut problems,
+    };
+    o.apply(&mut raw.database_url, "DATABASE_URL");
+    o.apply(&mut raw.discord_token, "DISCORD_TOKEN");
+    o.apply(&mut raw.application_id, "APPLICATION_ID");
+    o.apply(&mut raw.command_prefix, "COMMAND_PREFIX");
+    o.apply(&mut raw.gotd_api_url, "GOTD_API_URL");
+    o.apply(&mut raw.giantbomb_api_key, "GIANTBOMB_API_KEY");
+    o.apply(&mut raw.giantbomb_base_url, "GIANTBOMB_BASE_URL");
+    o.apply(&mut raw.giantbomb_timeout_secs, "GIANTBOMB_TIMEOUT_SECS");
+    o.apply(&mut raw.giantbomb_retries, "GIANTBOMB_RETRIES");
+    o.apply(&mut raw.game_fixtures_dir, "GAME_FIXTURES_DIR");
+    o.apply(&mut raw.game_cache_target, "GAME_CACHE_TARGET");
+    o.apply(&mut raw.game_cache_warm_secs, "GAME_CACHE_WARM_SECS");
+    o.apply(&mut raw.default_timezone, "DEFAULT_TIMEZONE");
+    o.apply(&mut raw.wyr_dir, "WYR_DIR");
+    o.apply(&mut raw.ops_channel_id, "OPS_CHANNEL_ID");
+    o.apply(&mut raw.giantbomb_failure_alert, "GIANTBOMB_FAILURE_ALERT");
+    o.apply(&mut raw.catch_up_grace_mins, "GOTD_CATCHUP_GRACE_MINS");
+    o.apply(&mut raw.no_repeat_days, "GOTD_NO_REPEAT_DAYS");
+    o.apply(
+      &mut raw.react.channel_cooldown_secs,
+      "REACT_CHANNEL_COOLDOWN_SECS",
+    );
+    o.apply(
+      &mut raw.react.rule_cooldown_secs,
+      "REACT_RULE_COOLDOWN_SECS",
+    );
+    o.apply(&mut raw.react.burst, "REACT_BURST");
+    o.apply(&mut raw.react.refill_secs, "REACT_REFILL_SECS");
+
+    let database_url = required(
+      raw.database_url,
+      "database_url / DATABASE_URL",
+      &mut problems,
+    );
+    let discord_token = required(
+      raw.discord_token,
+      "discord_token / DISCORD_TOKEN",
+      &mut problems,
+    );
+    let application_id = required(
+      raw.application_id,
+      "application_id / APPLICATION_ID",
+      &mut problems,
+    );
+
+    let default_timezone = raw
+      .default_timezone
+      .unwrap_or(String::from(LEGACY_TIMEZONE.name()));
+    if Tz::from_str(&default_timezone).is_err() {
+      problems.push(format!(
+        "default_timezone `{}` isn't an IANA timezone",
+        default_timezone
+      ));
+    }
+    let command_prefix = raw.command_prefix.unwrap_or(String::from("~"));
+    if command_prefix.is_empty() {
+      problems.push(String::from("command_prefix can't be empty"));
+    }
+    let giantbomb_timeout_secs = raw.giantbomb_timeout_secs.unwrap_or(10);
+    if giantbomb_timeout_secs == 0 {
+      problems.push(String::from("giantbomb_timeout_secs has to be at least 1"));
+    }
+    let game_cache_warm_secs = raw.game_cache_warm_secs.unwrap_or(90);
+    if game_cache_warm_secs == 0 {
+      problems.push(String::from("game_cache_warm_secs has to be at least 1"));
+    }
+    let refill_secs = raw.react.refill_secs.unwrap_or(2);
+    if refill_secs == 0 {
+      problems.push(String::from("react.refill_secs has to be at least 1"));
+    }
+
+    if !problems.is_empty() {
+      return Err(ConfigError { problems });
+    }
+
+    Ok(Self {
+      database_url: database_url.unwrap_or_default(),
+      discord_token: discord_token.unwrap_or_default(),
+      application_id: application_id.unwrap_or_default(),
+      command_prefix,
+      gotd_api_url: raw.gotd_api_url.unwrap_or(String::from(
+        "https://andrewbot-rs-xokx9.ondigitalocean.app/gb/games/random",
+      )),
+      giantbomb_api_key: raw.giantbomb_api_key.filter(|k| !k.trim().is_empty()),
+      giantbomb_base_url: raw
+        .giantbomb_base_url
+        .unwrap_or(String::from("https://www.giantbomb.com/api")),
+      giantbomb_timeout_secs,
+      giantbomb_retries: raw.giantbomb_retries.unwrap_or(3),
+      game_fixtures_dir: raw.game_fixtures_dir.map(PathBuf::from),
+      game_cache_target: raw.game_cache_target.unwrap_or(500),
+      game_cache_warm_secs,
+      default_timezone,
+      wyr_dir: PathBuf::from(raw.wyr_dir.unwrap_or(String::from("db/wyr"))),
+      ops_channel_id: raw.ops_channel_id,
+      giantbomb_failure_alert: raw.giantbomb_failure_alert.unwrap_or(3),
+      catch_up_grace_mins: raw.catch_up_grace_mins.unwrap_or(60),
+      no_repeat_days: raw.no_repeat_days.unwrap_or(90),
+      react: ReactConfig {
+        channel_cooldown_secs: raw.react.channel_cooldown_secs.unwrap_or(5),
+        rule_cooldown_secs: raw.react.rule_cooldown_secs.unwrap_or(30),
+        burst: raw.react.burst.unwrap_or(10),
+        refill_secs,
+      },
+    })
+  }
+}
+
+struct Overrides<'a, F: Fn(&str) -> Option<String>> {
+  env: &'a F,
+  problems: &'a mut Vec<String>,
+}
+
+impl<'a, F: Fn(&str) -> Option<String>> Overrides<'a, F> {
+  /**
+   * Replace the value with the env var when it's set. A value that won't
+   * parse is a problem rather than quietly falling back.
+   */
+  fn apply<T: FromStr>(&mut self, value: &mut Option<T>, name: &str) {
+    let var = match (self.env)(name) {
+      Some(v) if !v.trim().is_empty() => v,
+      _ => return,
+    };
+    match var.trim().parse::<T>() {
+      Ok(v) => *value = Some(v),
+      Err(_) => self
+        .problems
+        .push(format!("{} `{}` isn't a valid value", name, var)),
+    }
+  }
+}
+
+fn required<T>(value: Option<T>, name: &str, problems: &mut Vec<String>) -> Option<T> {
+  if value.is_none() {
+    problems.push(format!("{} is required", name));
+  }
+  value
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::collections::HashMap;
+
+  fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+    let vars = vars
+      .iter()
+      .map(|(k, v)| (String::from(*k), String::from(*v)))
+      .collect::<HashMap<String, String>>();
+    move |name| vars.get(name).cloned()
+  }
+
+  #[test]
+  fn env_overrides_the_file() {
+    let toml_str = r#"
+      database_url = "mysql://file"
+      discord_token = "file-token"
+      application_id = 1
+
+      [react]
+      burst = 4
+    "#;
+    let config = Config::from_sources(
+      toml_str,
+      env(&[("DATABASE_URL", "mysql://env"), ("REACT_BURST", "6")]),
+    )
+    .unwrap();
+
+    assert_eq!(config.database_url, "mysql://env");
+    assert_eq!(config.discord_token, "file-token");
+    assert_eq!(config.application_id, 1);
+    assert_eq!(config.react.burst, 6);
+    assert_eq!(config.command_prefix, "~");
+    assert_eq!(config.wyr_dir, PathBuf::from("db/wyr"));
+  }
+
+  #[test]
+  fn reports_every_problem_at_once() {
+    let err = Config::from_sources(
+      r#"default_timezone = "Mars/Olympus_Mons""#,
+      env(&[
+        ("APPLICATION_ID", "not-a-number"),
+        ("REACT_REFILL_SECS", "0"),
+      ]),
+    )
+    .unwrap_err();
+
+    assert_eq!(err.problems.len(), 6, "{}", err);
+    assert!(err.to_string().contains("DISCORD_TOKEN"));
+    assert!(err.to_string().contains("Mars/Olympus_Mons"));
+  }
+
+  #[test]
+  fn unknown_keys_are_a_problem() {
+    let err = Config::from_sources(
+      r#"discord_tokn = "typo""#,
+      env(&[
+        ("DATABASE_URL", "mysql://env"),
+        ("DISCORD_TOKEN", "t"),
+        ("APPLICATION_ID", "1"),
+      ]),
+    )
+    .unwrap_err();
+
+    assert_eq!(err.problems.len(), 1);
+  }
+}
@@ -3,6 +3,8 @@ extern crate diesel;
 
 mod clients;
 mod commands;
+mod config;
+mod react_limit;
 mod store;
 
 // use chrono::Utc;
@@ -10,36 +12,51 @@ mod store;
 use dotenv::dotenv;
 use serenity::{
     async_trait,
+    builder::CreateApplicationCommand,
     framework::standard::{
         macros::{group, hook},
         StandardFramework,
     },
+    http::Http,
     model::{
-        channel::{Message, ReactionType},
+        channel::Message,
         gateway::Ready,
         guild::Guild,
-        id::{ChannelId, GuildId},
+        id::GuildId,
         interactions::{
             application_command::{ApplicationCommand, ApplicationCommandOptionType},
-            Interaction, InteractionApplicationCommandCallbackDataFlags, InteractionResponseType,
+            Interaction,
         },
     },
     prelude::{Client, Context, EventHandler},
 };
 // use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use diesel::prelude::*;
 use diesel::r2d2::ConnectionManager;
-use tracing::{debug, error, info, instrument};
-
-use chrono::{FixedOffset, Utc};
+use tracing::{debug, error, info, instrument, warn};
+
+use chrono::Utc;
+use chrono_tz::Tz;
+use clients::game_cache::CachedGameSource;
+use clients::game_filter::{DECADES, PLATFORMS};
+use clients::game_source::{FixtureGameSource, GameSource, HttpGameSource};
+use clients::ops::Ops;
 use commands::ping::*;
+use commands::CommandState;
+use config::Config;
 use cron::Schedule;
-use std::str::FromStr;
-use store::model::GotdJob;
+use react_limit::{Limits, ReactLimiter};
+use store::model::{
+    GotdJob, WyrPoll, JOB_KIND_WYR, TRIGGER_EMOJI, TRIGGER_REGEX, TRIGGER_USER, TRIGGER_WORD,
+};
 use store::mysql_store::GotdMysqlStore;
-use store::storage::GotdDb;
+use store::react_store::ReactStore;
+use store::storage::{GameCacheDb, GotdDb, ReactDb, WyrDb};
+use store::wyr_store::WyrStore;
 use tokio::sync::mpsc;
 
 enum Command {
@@ -50,38 +67,224 @@ enum Command {
 struct Job {
     job: GotdJob,
     schedule: Schedule,
-    pub tz: FixedOffset,
-    next_date: chrono::DateTime<FixedOffset>,
+    pub tz: Tz,
+    // none once the schedule has nothing left to run
+    next_date: Option<chrono::DateTime<Tz>>,
 }
 
 impl Job {
-    pub fn new(job: GotdJob) -> Self {
-        let schedule = Schedule::from_str(job.cron_schedule.as_str()).unwrap();
-        let tz = FixedOffset::west(5 * 3600);
-        let next_date = schedule.upcoming(tz).take(1).next().unwrap();
-        Self {
+    /**
+     * A job that can't be scheduled is logged and skipped, it shouldn't
+     * take every other job down with it
+     */
+    pub fn new(job: GotdJob) -> Option<Self> {
+        let schedule = match job.schedule() {
+            Ok(s) => s,
+            Err(why) => {
+                warn!(
+                    "Skipping job {} with bad cron '{}': {}",
+                    job.id, job.cron_schedule, why
+                );
+                return None;
+            }
+        };
+        let tz = job.tz();
+        let next_date = schedule.upcoming(tz).next();
+        if next_date.is_none() {
+            warn!(
+                "Skipping job {}, cron '{}' never runs again",
+                job.id, job.cron_schedule
+            );
+            return None;
+        }
+
+        Some(Self {
             job,
             schedule,
             next_date,
             tz,
-        }
+        })
+    }
+
+    pub fn id(&self) -> i32 {
+        self.job.id
     }
 
     pub fn channel_id(&self) -> u64 {
         self.job.channel_id
     }
 
-    pub fn get_date(&self) -> chrono::DateTime<FixedOffset> {
+    pub fn get_date(&self) -> Option<chrono::DateTime<Tz>> {
         self.next_date
     }
 
     pub fn advance(&mut self) {
-        self.next_date = self.schedule.upcoming(self.tz).take(1).next().unwrap();
+        self.next_date = self.schedule.upcoming(self.tz).next();
+        if self.next_date.is_none() {
+            warn!(
+                "Job {} has run for the last time, cron '{}'",
+                self.id(),
+                self.job.cron_schedule
+            );
+        }
     }
 }
 
-struct Handler {
+/**
+ * Send whatever the job posts and remember when it went out, so a restart
+ * knows whether it still owes the channel a post.
+ */
+async fn run_job(
+    http: &Arc<Http>,
     db: Arc<GotdMysqlStore>,
+    wyr: &WyrStore,
+    ops: &Ops,
+    config: &Config,
+    games: &dyn GameSource,
+    job: &GotdJob,
+) {
+    let sent = match job.kind.as_str() {
+        JOB_KIND_WYR => {
+            commands::wyr::send_wyr(http, Arc::clone(&db), wyr, job.channel_id, job.guild_id).await
+        }
+        // a filter that can't be read skips the post rather than sending any game
+        _ => match job.game_filter() {
+            Ok(filter) => {
+                commands::game::send_gotd(
+                    http,
+                    Arc::clone(&db),
+                    ops,
+                    config,
+                    games,
+                    job.channel_id,
+                    &filter,
+                )
+                .await
+            }
+            Err(why) => Err(format!("bad game filter: {}", why).into()),
+        },
+    };
+
+    match sent {
+        Ok(()) => {
+            if let Err(why) = db.set_last_sent(job.id, Utc::now().naive_utc()) {
+                error!("Failed to record last send for job {}: {}", job.id, why);
+            }
+        }
+        Err(why) => {
+            error!("Failed to cron {} job {}: {}", job.kind, job.id, why);
+            ops.scheduler_error(
+                http,
+                &format!("send {} job {} to <#{}>", job.kind, job.id, job.channel_id),
+                &why.to_string(),
+            )
+            .await;
+        }
+    }
+}
+
+/**
+ * The when options shared by every command that schedules a post
+ */
+fn schedule_options<'a>(
+    cmd: &'a mut CreateApplicationCommand,
+    default_timezone: &str,
+) -> &'a mut CreateApplicationCommand {
+    cmd.create_option(|option| {
+        option
+            .name("time")
+            .description("When to send it to the channel")
+            .kind(ApplicationCommandOptionType::String)
+            .required(false)
+            .add_string_choice("Morning, around 8am", "morning")
+            .add_string_choice("Midday, around 12pm", "noon")
+            .add_string_choice("Evening, around 8pm", "night")
+    })
+    .create_option(|option| {
+        option
+            .name("at")
+            .description("Exact time of day as HH:MM, like 09:30")
+            .kind(ApplicationCommandOptionType::String)
+            .required(false)
+    })
+    .create_option(|option| {
+        option
+            .name("days")
+            .description("daily, weekdays, weekends or a list like Mon,Wed,Fri (default daily)")
+            .kind(ApplicationCommandOptionType::String)
+            .required(false)
+    })
+    .create_option(|option| {
+        option
+            .name("cron")
+            .description("Full cron expression (sec min hour day month weekday year)")
+            .kind(ApplicationCommandOptionType::String)
+            .required(false)
+    })
+    .create_option(|option| {
+        option
+            .name("timezone")
+            .description(format!(
+                "IANA timezone, like America/Los_Angeles (default {})",
+                default_timezone
+            ))
+            .kind(ApplicationCommandOptionType::String)
+            .required(false)
+    })
+}
+
+/**
+ * The platform and decade options shared by /game and /gotd
+ */
+fn game_filter_options(cmd: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    cmd.create_option(|option| {
+        option
+            .name("platform")
+            .description("Only games on this platform")
+            .kind(ApplicationCommandOptionType::String)
+            .required(false);
+        for p in PLATFORMS.iter() {
+            option.add_string_choice(p.name, p.key);
+        }
+        option
+    })
+    .create_option(|option| {
+        option
+            .name("decade")
+            .description("Only games released in this decade")
+            .kind(ApplicationCommandOptionType::Integer)
+            .required(false);
+        for d in DECADES.iter() {
+            option.add_int_choice(format!("{}s", d), *d);
+        }
+        option
+    })
+}
+
+/**
+ * Wait for ctrl-c, or a SIGTERM from whatever is running the bot
+ */
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        let mut term = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {},
+            _ = term.recv() => {},
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+struct Handler {
+    state: CommandState,
+    react_limit: ReactLimiter,
+    // ready fires again on every reconnect, only announce the first one
+    announced: AtomicBool,
 }
 
 #[async_trait]
@@ -94,21 +297,28 @@ impl EventHandler for Handler {
     }
 
     async fn message(&self, ctx: Context, new_message: Message) {
-        let kevin_toms = "<:KevinToms:776453874310709249>";
-        let o_kevin_toms = "<:KevinToms:885148040619511808>";
-        if new_message.content.contains(kevin_toms) {
-            if let Err(why) = new_message
-                .react(&ctx.http, ReactionType::from_str(kevin_toms).unwrap())
-                .await
-            {
-                error!("Failed to react with Kevin Toms to Kevin Toms: {}", why);
-            }
-        } else if new_message.content.contains(o_kevin_toms) {
-            if let Err(why) = new_message
-                .react(&ctx.http, ReactionType::from_str(o_kevin_toms).unwrap())
-                .await
-            {
-                error!("Failed to react with Kevin Toms to Kevin Toms: {}", why);
+        // DMs have no guild, so they get the default rules
+        let guild_id = new_message.guild_id.unwrap_or_default().0;
+        let rules =
+            self.state
+                .react
+                .matching(guild_id, new_message.author.id.0, &new_message.content);
+        let allowed = self.react_limit.check(
+            new_message.channel_id.0,
+            &rules
+                .iter()
+                .map(|r| (r.id, r.reactions.len()))
+                .collect::<Vec<(i32, usize)>>(),
+            Instant::now(),
+        );
+        for rule in rules.iter().filter(|r| allowed.contains(&r.id)) {
+            for reaction in rule.reactions.iter() {
+                if let Err(why) = new_message.react(&ctx.http, reaction.clone()).await {
+                    error!(
+                        "Failed to react with {} for rule {}: {}",
+                        reaction, rule.id, why
+                    );
+                }
             }
         }
     }
@@ -125,30 +335,54 @@ impl EventHandler for Handler {
     async fn ready(&self, ctx: Context, ready: Ready) {
         info!("{} ready", ready.user.name);
 
+        let shards = ready.shard.map(|[_, total]| total).unwrap_or(1);
+        if !self.announced.swap(true, Ordering::SeqCst) {
+            self.state.ops.startup(&ctx.http, shards).await;
+        }
+
         if let Err(why) =
             ApplicationCommand::set_global_application_commands(&ctx.http, |commands| {
                 commands
                     .create_application_command(|cmd| {
-                        cmd.name("game")
-                            .description("Return a random Game of the Day from GiantBomb")
+                        game_filter_options(
+                            cmd.name("game")
+                                .description("Return a random Game of the Day from GiantBomb"),
+                        )
+                    })
+                    .create_application_command(|cmd| {
+                        game_filter_options(schedule_options(
+                            cmd.name("gotd")
+                                .description("Schedule a random game be send to this channel each day"),
+                            &self.state.config.default_timezone,
+                        ))
                     })
                     .create_application_command(|cmd| {
-                        cmd.name("gotd")
-                            .description("Schedule a random game be send to this channel each day")
+                        cmd.name("gotd-stop")
+                            .description("Stop a scheduled Game of the Day or Would You Rather")
                             .create_option(|option| {
                                 option
-                                    .name("time")
-                                    .description("When to send the game to the channel")
-                                    .kind(ApplicationCommandOptionType::String)
-                                    .required(true)
-                                    .add_string_choice("Morning, around 8am EST", "morning")
-                                    .add_string_choice("Midday, around 12pm EST", "noon")
-                                    .add_string_choice("Evening, around 8pm EST", "night")
+                                    .name("id")
+                                    .description(
+                                        "Which schedule to stop, needed when the channel has more than one",
+                                    )
+                                    .kind(ApplicationCommandOptionType::Integer)
+                                    .required(false)
                             })
                     })
                     .create_application_command(|cmd| {
-                        cmd.name("gotd-stop")
-                            .description("Stop pulling a Game of the Day")
+                        cmd.name("gotd-list")
+                            .description("List every scheduled post in this server")
+                    })
+                    .create_application_command(|cmd| {
+                        cmd.name("gotd-history")
+                            .description("Look back at every Game of the Day posted to this channel")
+                            .create_option(|option| {
+                                option
+                                    .name("number")
+                                    .description("Show the game with this number in the history again")
+                                    .kind(ApplicationCommandOptionType::Integer)
+                                    .required(false)
+                            })
                     })
                     .create_application_command(|cmd| {
                         cmd.name("mem")
@@ -157,6 +391,125 @@ impl EventHandler for Handler {
                     .create_application_command(|cmd| {
                         cmd.name("wyr")
                             .description("Prompt this channel with a 'Would you rather?' poll")
+                            .create_option(|option| {
+                                option
+                                    .name("collection")
+                                    .description("Where to pull the poll from (default top-all)")
+                                    .kind(ApplicationCommandOptionType::String)
+                                    .required(false)
+                                    .add_string_choice("Hot", "hot")
+                                    .add_string_choice("New", "new")
+                                    .add_string_choice("Top this year", "top-year")
+                                    .add_string_choice("Top of all time", "top-all")
+                                    .add_string_choice("Submitted by this server", "community")
+                                    .add_string_choice("Any of them", "any")
+                            })
+                            .create_option(|option| {
+                                option
+                                    .name("duration")
+                                    .description("Close the poll and announce a winner after this long")
+                                    .kind(ApplicationCommandOptionType::Integer)
+                                    .required(false)
+                                    .add_int_choice("5 minutes", 5)
+                                    .add_int_choice("15 minutes", 15)
+                                    .add_int_choice("1 hour", 60)
+                                    .add_int_choice("4 hours", 240)
+                                    .add_int_choice("1 day", 1440)
+                            })
+                    })
+                    .create_application_command(|cmd| {
+                        cmd.name("wyr-submit")
+                            .description("Submit your own 'Would you rather?' poll for moderators to approve")
+                            .create_option(|option| {
+                                option
+                                    .name("title")
+                                    .description("The question, like: WYR fight one horse-sized duck or...")
+                                    .kind(ApplicationCommandOptionType::String)
+                                    .required(true)
+                            });
+                        for n in 1..=5 {
+                            cmd.create_option(|option| {
+                                option
+                                    .name(format!("option{}", n))
+                                    .description(format!("Choice number {}", n))
+                                    .kind(ApplicationCommandOptionType::String)
+                                    .required(n <= 2)
+                            });
+                        }
+                        cmd
+                    })
+                    .create_application_command(|cmd| {
+                        schedule_options(
+                            cmd.name("wyr-schedule")
+                                .description("Schedule a 'Would you rather?' poll be sent to this channel"),
+                            &self.state.config.default_timezone,
+                        )
+                    })
+                    .create_application_command(|cmd| {
+                        cmd.name("wyr-stats")
+                            .description("How you vote on 'Would you rather?' polls, and who votes most")
+                            .create_option(|option| {
+                                option
+                                    .name("user")
+                                    .description("Whose stats to show (default you)")
+                                    .kind(ApplicationCommandOptionType::User)
+                                    .required(false)
+                            })
+                    })
+                    .create_application_command(|cmd| {
+                        cmd.name("react-rule")
+                            .description("Have the bot react to messages with emoji")
+                            .create_option(|sub| {
+                                sub.name("add")
+                                    .description("Moderators only: react when a message matches")
+                                    .kind(ApplicationCommandOptionType::SubCommand)
+                                    .create_sub_option(|option| {
+                                        option
+                                            .name("trigger")
+                                            .description("What to look for in a message")
+                                            .kind(ApplicationCommandOptionType::String)
+                                            .required(true)
+                                            .add_string_choice("An emoji", TRIGGER_EMOJI)
+                                            .add_string_choice("A word", TRIGGER_WORD)
+                                            .add_string_choice("A regex", TRIGGER_REGEX)
+                                            .add_string_choice("Anything from a user", TRIGGER_USER)
+                                    })
+                                    .create_sub_option(|option| {
+                                        option
+                                            .name("value")
+                                            .description("The emoji, word, regex or user mention to look for")
+                                            .kind(ApplicationCommandOptionType::String)
+                                            .required(true)
+                                    })
+                                    .create_sub_option(|option| {
+                                        option
+                                            .name("reactions")
+                                            .description("Up to 5 emoji to react with, separated by spaces")
+                                            .kind(ApplicationCommandOptionType::String)
+                                            .required(true)
+                                    })
+                            })
+                            .create_option(|sub| {
+                                sub.name("remove")
+                                    .description("Moderators only: stop reacting for a rule")
+                                    .kind(ApplicationCommandOptionType::SubCommand)
+                                    .create_sub_option(|option| {
+                                        option
+                                            .name("id")
+                                            .description("Which rule to remove, see /react-rule list")
+                                            .kind(ApplicationCommandOptionType::Integer)
+                                            .required(true)
+                                    })
+                            })
+                            .create_option(|sub| {
+                                sub.name("list")
+                                    .description("List every react rule in this server")
+                                    .kind(ApplicationCommandOptionType::SubCommand)
+                            })
+                    })
+                    .create_application_command(|cmd| {
+                        cmd.name("wyr-reload")
+                            .description("Owner only: re-read the Would You Rather polls from disk")
                     })
             })
             .await
@@ -264,7 +617,7 @@ impl EventHandler for Handler {
 
             let actx = Arc::new(ctx);
             let actxc = Arc::clone(&actx);
-            if let Err(why) = commands::handler(actx, &self.db, &command).await {
+            if let Err(why) = commands::handler(actx, &self.state, &command).await {
                 let ctx_clone = Arc::clone(&actxc);
                 error!("Failed to handle to command: {}", why);
                 if let Err(why_cmd) = commands::respond(
@@ -281,50 +634,11 @@ impl EventHandler for Handler {
             return;
         }
 
-        if let Interaction::MessageComponent(message) = interaction {
-            let mut id_iter = message.data.custom_id.split("::");
-            let interation_type = id_iter.next();
-
-            let is_wyr = match interation_type {
-                Some(s) => s == "wyr",
-                None => false,
-            };
-
-            if !is_wyr {
-                return;
-            }
-
-            let m_opt = id_iter.next();
-            let m_wyr_id = id_iter.next();
-
-            if m_opt.is_none() || m_wyr_id.is_none() {
-                return;
-            }
-
-            let opt = m_opt.unwrap();
-            let wyr_id = m_wyr_id.unwrap();
-            let userid = &message.user.id;
-            let username = &message.user.name;
-
-            info!(
-                "wyr message response {} : {} : {} : {}",
-                userid, username, opt, wyr_id
-            );
-
-            // save everything to database
-            // let _db = &self.db;
-
-            if let Err(why) = message
-                .create_interaction_response(&ctx.http, |res| {
-                    res.kind(InteractionResponseType::ChannelMessageWithSource)
-                        .interaction_response_data(|m| {
-                            m.flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL)
-                                .content("👍")
-                        })
-                })
-                .await
+        if let Interaction::MessageComponent(component) = interaction {
+            if let Err(why) =
+                commands::component_handler(Arc::new(ctx), &self.state, &component).await
             {
-                error!("Failed to respond to reply: {}", why);
+                error!("Failed to handle component: {}", why);
             }
         }
     }
@@ -347,18 +661,19 @@ async fn main() {
     dotenv().ok();
     tracing_subscriber::fmt::init();
 
-    let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL env var required");
-    let manager = ConnectionManager::<MysqlConnection>::new(db_url);
-    let pool = diesel::r2d2::Pool::new(manager).expect("Failed to create connection pool");
+    let config = match Config::load() {
+        Ok(c) => Arc::new(c),
+        Err(why) => {
+            error!("Failed to load config, {}", why);
+            std::process::exit(1);
+        }
+    };
 
-    let token = std::env::var("DISCORD_TOKEN").expect("DISCORD_TOKEN env var required");
-    let app_id: u64 = std::env::var("APPLICATION_ID")
-        .expect("APPLICATION_ID env var required ")
-        .parse()
-        .expect("APPLICATION_ID not valid");
+    let manager = ConnectionManager::<MysqlConnection>::new(config.database_url.as_str());
+    let pool = diesel::r2d2::Pool::new(manager).expect("Failed to create connection pool");
 
     let framework = StandardFramework::new()
-        .configure(|c| c.prefix("~"))
+        .configure(|c| c.prefix(config.command_prefix.as_str()))
         .before(before)
         .group(&GENERAL_GROUP);
 
@@ -373,21 +688,69 @@ async fn main() {
     let txj = tx.clone();
     let db = Arc::new(GotdMysqlStore::new(pool));
     let adb = Arc::clone(&db);
+    let wyr = Arc::new(WyrStore::load_or_empty(&config.wyr_dir));
+    let awyr = Arc::clone(&wyr);
+    // no reactions beats no bot, a guild gets its rules back when one is edited
+    let react_rules = db.get_all_react_rules().unwrap_or_else(|why| {
+        error!("Failed to load react rules, starting without any: {}", why);
+        vec![]
+    });
+    let react = Arc::new(ReactStore::new(&react_rules));
+    let ops = Arc::new(Ops::new(
+        config.ops_channel_id,
+        config.giantbomb_failure_alert,
+    ));
+    let aops = Arc::clone(&ops);
+    let games: Arc<dyn GameSource> = match &config.game_fixtures_dir {
+        Some(dir) => Arc::new(FixtureGameSource::load(dir).expect("Failed to load game fixtures")),
+        None => {
+            let cached = Arc::new(CachedGameSource::new(
+                Arc::new(HttpGameSource::new(Arc::clone(&config))),
+                Arc::clone(&db) as Arc<dyn GameCacheDb + Send + Sync>,
+            ));
+            if config.game_cache_target > 0 {
+                let warm = Arc::clone(&cached);
+                let target = config.game_cache_target;
+                let every = Duration::from_secs(config.game_cache_warm_secs);
+                tokio::spawn(async move { warm.prewarm(target, every).await });
+            }
+            cached
+        }
+    };
+    let agames = Arc::clone(&games);
+    let react_limit = ReactLimiter::new(Limits {
+        channel_cooldown: Duration::from_secs(config.react.channel_cooldown_secs),
+        rule_cooldown: Duration::from_secs(config.react.rule_cooldown_secs),
+        bucket_size: config.react.burst,
+        refill_every: Duration::from_secs(config.react.refill_secs),
+    });
 
-    let mut client = Client::builder(&token)
-        .event_handler(Handler { db })
-        .application_id(app_id)
+    let mut client = Client::builder(&config.discord_token)
+        .event_handler(Handler {
+            state: CommandState {
+                db,
+                wyr,
+                react,
+                ops: Arc::clone(&ops),
+                config: Arc::clone(&config),
+                games: Arc::clone(&games),
+            },
+            react_limit,
+            announced: AtomicBool::new(false),
+        })
+        .application_id(config.application_id)
         .framework(framework)
         .await
         .expect("Err creating client");
 
-    let dev_channel_id: u64 = 689814575306244110;
-    if let Err(why) = ChannelId(dev_channel_id)
-        .send_message(&client.cache_and_http.http, |m| m.content("I'm alive!"))
-        .await
-    {
-        error!("Failed to send startup message: {}", why);
-    }
+    let shard_manager = Arc::clone(&client.shard_manager);
+    let shutdown_http = Arc::clone(&client.cache_and_http.http);
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        info!("Shutting down");
+        ops.shutdown(&shutdown_http).await;
+        shard_manager.lock().await.shutdown_all().await;
+    });
 
     tokio::spawn(async move {
         info!("Starting gotd schedule thread");
@@ -405,37 +768,101 @@ async fn main() {
         }
     });
 
+    // how far back to look for games that should have gone out while the bot was down
+    let catch_up_grace = chrono::Duration::minutes(config.catch_up_grace_mins);
+
     let http = Arc::clone(&client.cache_and_http.http);
     tokio::spawn(async move {
         info!("Starting command thread");
         let mut jobs = Vec::<Job>::new();
+        let mut polls = Vec::<WyrPoll>::new();
+        let mut caught_up = false;
 
         while let Some(message) = rx.recv().await {
             match message {
                 Command::Update() => {
                     debug!("Updating jobs");
-                    match adb.get_all_active_sched() {
+                    let loaded = adb.get_all_active_sched();
+                    aops.scheduler_lookup(
+                        &http,
+                        "load schedules",
+                        loaded.as_ref().err().map(|why| why.to_string()),
+                    )
+                    .await;
+                    match loaded {
                         Ok(records) => {
                             jobs.clear();
-                            jobs.extend(records.into_iter().map(|c| Job::new(c)));
+                            jobs.extend(records.into_iter().filter_map(Job::new));
+
+                            if !caught_up {
+                                caught_up = true;
+                                let now = Utc::now();
+                                for job in jobs.iter() {
+                                    if let Some(missed) = job.job.missed_date(&now, catch_up_grace)
+                                    {
+                                        info!(
+                                            "Catching up job {} missed at {} for channel {}",
+                                            job.id(),
+                                            missed,
+                                            job.channel_id()
+                                        );
+                                        run_job(
+                                            &http,
+                                            Arc::clone(&adb),
+                                            &awyr,
+                                            &aops,
+                                            &config,
+                                            agames.as_ref(),
+                                            &job.job,
+                                        )
+                                        .await;
+                                    }
+                                }
+                            }
                         }
                         Err(why) => error!("Failed to get crons for guild: {}", why),
                     }
+
+                    let open = adb.get_open_polls();
+                    aops.scheduler_lookup(
+                        &http,
+                        "load open polls",
+                        open.as_ref().err().map(|why| why.to_string()),
+                    )
+                    .await;
+                    match open {
+                        Ok(records) => polls = records,
+                        Err(why) => error!("Failed to get open wyr polls: {}", why),
+                    }
                 }
                 Command::Check() => {
                     debug!("Checking {} jobs", jobs.len());
                     for job in jobs.iter_mut() {
                         let adb = Arc::clone(&adb);
-                        let datetime = job.get_date();
                         let now = Utc::now().with_timezone(&job.tz);
 
-                        if datetime < now {
+                        if job.get_date().is_some_and(|d| d < now) {
                             job.advance();
-                            if let Err(why) =
-                                commands::game::send_gotd(&http, adb, job.channel_id()).await
-                            {
-                                error!("Failed to cron {}", why);
-                            }
+                            run_job(&http, adb, &awyr, &aops, &config, agames.as_ref(), &job.job)
+                                .await;
+                        }
+                    }
+
+                    let now = Utc::now().naive_utc();
+                    let (due, open): (Vec<WyrPoll>, Vec<WyrPoll>) =
+                        polls.drain(..).partition(|p| p.is_due(now));
+                    polls = open;
+                    for poll in due.iter() {
+                        if let Err(why) =
+                            commands::wyr::close_poll(&http, Arc::clone(&adb), &awyr, poll).await
+                        {
+                            error!("Failed to close wyr poll {}: {}", poll.id, why);
+                            aops.scheduler_error(
+                                &http,
+                                &format!("close wyr poll {}", poll.id),
+                                &why.to_string(),
+                            )
+                            .await;
                         }
                     }
                 }
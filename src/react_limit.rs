@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+
+// forget cooldowns that ran out once this many have piled up
+const PRUNE_AT: usize = 1024;
+// how often to log how many reactions were held back
+const SUMMARY_EVERY: Duration = Duration::from_secs(60);
+
+/**
+ * Why reactions to a message were held back
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Verdict {
+  Allowed,
+  ChannelCooldown,
+  RuleCooldown,
+  NoTokens,
+}
+
+/**
+ * How many reactions each limit has held back since startup
+ */
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Suppressed {
+  pub channel: u64,
+  pub rule: u64,
+  pub global: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Limits {
+  // after the bot reacts in a channel it stays quiet there this long
+  pub channel_cooldown: Duration,
+  // after a rule fires it stays quiet everywhere this long
+  pub rule_cooldown: Duration,
+  // the most reactions the bot can send in a burst
+  pub bucket_size: u32,
+  // how long it takes to earn back one reaction
+  pub refill_every: Duration,
+}
+
+struct State {
+  channels: HashMap<u64, Instant>,
+  rules: HashMap<i32, Instant>,
+  tokens: f64,
+  refilled_at: Instant,
+  suppressed: Suppressed,
+  logged_at: Instant,
+}
+
+/**
+ * Keeps auto-reactions from running into discord's rate limits when a
+ * channel spams a trigger. Every reaction spends a token from one bucket
+ * shared by all guilds, on top of the channel and rule cooldowns.
+ */
+pub struct ReactLimiter {
+  limits: Limits,
+  state: Mutex<State>,
+}
+
+impl ReactLimiter {
+  pub fn new(limits: Limits) -> Self {
+    let now = Instant::now();
+    Self {
+      state: Mutex::new(State {
+        channels: HashMap::new(),
+        rules: HashMap::new(),
+        tokens: limits.bucket_size as f64,
+        refilled_at: now,
+        suppressed: Suppressed::default(),
+        logged_at: now,
+      }),
+      limits,
+    }
+  }
+
+  /**
+   * Decide which of the rules a message triggered may send their reactions,
+   * given as `(rule id, reaction count)`. The channel cooldown and the token
+   * bucket are checked once for the whole message, then each rule's own
+   * cooldown. Allowed rules start their cooldowns and spend a token per
+   * reaction. Returns the ids of the allowed rules.
+   */
+  pub fn check(&self, channel_id: u64, rules: &[(i32, usize)], now: Instant) -> Vec<i32> {
+    if rules.is_empty() {
+      return vec![];
+    }
+
+    let mut state = self.state.lock().unwrap();
+    self.refill(&mut state, now);
+
+    let mut verdicts = Vec::<(i32, usize, Verdict)>::new();
+    let channel_cooling = cooling(
+      state.channels.get(&channel_id),
+      self.limits.channel_cooldown,
+      now,
+    );
+    for (rule_id, reactions) in rules.iter() {
+      let verdict = if channel_cooling {
+        Verdict::ChannelCooldown
+      } else if cooling(state.rules.get(rule_id), self.limits.rule_cooldown, now) {
+        Verdict::RuleCooldown
+      } else {
+        Verdict::Allowed
+      };
+      verdicts.push((*rule_id, *reactions, verdict));
+    }
+
+    // the bucket covers everything the message would send, or none of it
+    let wanted = verdicts
+      .iter()
+      .filter(|(_, _, v)| *v == Verdict::Allowed)
+      .map(|(_, r, _)| *r)
+      .sum::<usize>();
+    if state.tokens < wanted as f64 {
+      for (_, _, v) in verdicts
+        .iter_mut()
+        .filter(|(_, _, v)| *v == Verdict::Allowed)
+      {
+        *v = Verdict::NoTokens;
+      }
+    } else if wanted > 0 {
+      state.tokens -= wanted as f64;
+      state.channels.insert(channel_id, now);
+    }
+
+    let mut allowed = Vec::<i32>::new();
+    for (rule_id, reactions, verdict) in verdicts.iter() {
+      match verdict {
+        Verdict::Allowed => {
+          state.rules.insert(*rule_id, now);
+          allowed.push(*rule_id);
+          continue;
+        }
+        Verdict::ChannelCooldown => state.suppressed.channel += *reactions as u64,
+        Verdict::RuleCooldown => state.suppressed.rule += *reactions as u64,
+        Verdict::NoTokens => state.suppressed.global += *reactions as u64,
+      }
+      debug!(
+        "Suppressed {} reaction(s) for rule {} in channel {}: {:?}",
+        reactions, rule_id, channel_id, verdict
+      );
+    }
+    self.prune(&mut state, now);
+
+    if allowed.len() < verdicts.len() && now.duration_since(state.logged_at) >= SUMMARY_EVERY {
+      state.logged_at = now;
+      info!(
+        "Reactions suppressed so far: {} by channel cooldown, {} by rule cooldown, {} by the global limit",
+        state.suppressed.channel, state.suppressed.rule, state.suppressed.global
+      );
+    }
+    allowed
+  }
+
+  fn refill(&self, state: &mut State, now: Instant) {
+    let elapsed = now.duration_since(state.refilled_at).as_secs_f64();
+    let earned = elapsed / self.limits.refill_every.as_secs_f64().max(f64::EPSILON);
+    state.tokens = (state.tokens + earned).min(self.limits.bucket_size as f64);
+    state.refilled_at = now;
+  }
+
+  fn prune(&self, state: &mut State, now: Instant) {
+    if state.channels.len() > PRUNE_AT {
+      let cooldown = self.limits.channel_cooldown;
+      state
+        .channels
+        .retain(|_, at| now.duration_since(*at) < cooldown);
+    }
+    if state.rules.len() > PRUNE_AT {
+      let cooldown = self.limits.rule_cooldown;
+      state
+        .rules
+        .retain(|_, at| now.duration_since(*at) < cooldown);
+    }
+  }
+}
+
+fn cooling(last: Option<&Instant>, cooldown: Duration, now: Instant) -> bool {
+  match last {
+    Some(at) => now.duration_since(*at) < cooldown,
+    None => false,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn suppressed(l: &ReactLimiter) -> Suppressed {
+    l.state.lock().unwrap().suppressed.clone()
+  }
+
+  fn limiter() -> ReactLimiter {
+    ReactLimiter::new(Limits {
+      channel_cooldown: Duration::from_secs(5),
+      rule_cooldown: Duration::from_secs(30),
+      bucket_size: 3,
+      refill_every: Duration::from_secs(10),
+    })
+  }
+
+  #[test]
+  fn cooldowns_hold_back_repeats() {
+    let l = limiter();
+    let start = Instant::now();
+    let later = start + Duration::from_secs(1);
+    assert_eq!(l.check(1, &[(7, 1)], start), vec![7]);
+    assert_eq!(l.check(1, &[(7, 1)], later), Vec::<i32>::new());
+    assert_eq!(l.check(2, &[(7, 1), (8, 1)], later), vec![8]);
+    assert_eq!(
+      l.check(1, &[(9, 1)], start + Duration::from_secs(6)),
+      vec![9]
+    );
+    assert_eq!(
+      suppressed(&l),
+      Suppressed {
+        channel: 1,
+        rule: 1,
+        global: 0
+      }
+    );
+  }
+
+  #[test]
+  fn every_rule_on_a_message_gets_to_react() {
+    let l = limiter();
+    let start = Instant::now();
+    assert_eq!(l.check(1, &[(1, 1), (2, 1)], start), vec![1, 2]);
+    assert_eq!(suppressed(&l), Suppressed::default());
+  }
+
+  #[test]
+  fn bucket_runs_dry_and_refills() {
+    let l = limiter();
+    let start = Instant::now();
+    assert_eq!(l.check(1, &[(1, 2)], start), vec![1]);
+    assert_eq!(l.check(2, &[(2, 1), (3, 1)], start), Vec::<i32>::new());
+    assert_eq!(l.check(2, &[(2, 1)], start), vec![2]);
+    assert_eq!(
+      l.check(3, &[(3, 1)], start + Duration::from_secs(10)),
+      vec![3]
+    );
+    assert_eq!(suppressed(&l).global, 2);
+  }
+}
@@ -1,4 +1,6 @@
 pub mod model;
 pub mod mysql_store;
+pub mod react_store;
 pub mod schema;
 pub mod storage;
+pub mod wyr_store;
@@ -1,4 +1,37 @@
-use super::schema::gotd_schedules;
+use super::schema::{
+  game_cache, gotd_history, gotd_schedules, react_rules, wyr_polls, wyr_shown, wyr_submissions,
+  wyr_votes,
+};
+use super::wyr_store::Wyr;
+use crate::clients::game_filter::GameFilter;
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
+use cron::Schedule;
+use std::str::FromStr;
+use tracing::warn;
+
+/**
+ * The timezone schedules ran in before one could be chosen. The timezone
+ * migration wrote it into every existing row, and it's the built in default
+ * for `default_timezone`.
+ */
+pub const LEGACY_TIMEZONE: Tz = Tz::America__New_York;
+
+/**
+ * What a schedule sends when it fires
+ */
+pub const JOB_KIND_GOTD: &str = "gotd";
+pub const JOB_KIND_WYR: &str = "wyr";
+
+/**
+ * How a job kind is shown to people
+ */
+pub fn job_kind_label(kind: &str) -> &'static str {
+  match kind {
+    JOB_KIND_WYR => "Would You Rather",
+    _ => "Game of the Day",
+  }
+}
 
 #[derive(Identifiable, Queryable, Debug)]
 #[table_name = "gotd_schedules"]
@@ -11,6 +44,81 @@ pub struct GotdJob {
   pub created_on_ts: Option<chrono::NaiveDateTime>,
   pub created_by_id: u64,
   pub is_deleted: bool,
+  pub timezone: String,
+  pub last_sent_ts: Option<chrono::NaiveDateTime>,
+  pub kind: String,
+  pub game_platform: Option<String>,
+  pub game_decade: Option<i32>,
+}
+
+impl GotdJob {
+  /**
+   * What games this job posts. A filter that no longer parses is an
+   * error, the job shouldn't fall back on posting any game.
+   */
+  pub fn game_filter(&self) -> Result<GameFilter, String> {
+    GameFilter::parse(
+      self.game_platform.as_deref(),
+      self.game_decade.map(i64::from),
+    )
+  }
+
+  /**
+   * The IANA timezone this job runs in. `/gotd` only saves zones that
+   * parse, so the fallback is for rows edited by hand. It's the legacy
+   * zone rather than the configured default, so changing that setting
+   * never moves a schedule that already exists.
+   */
+  pub fn tz(&self) -> Tz {
+    Tz::from_str(&self.timezone).unwrap_or_else(|why| {
+      warn!(
+        "Job {} has unknown timezone '{}', using {}: {}",
+        self.id,
+        self.timezone,
+        LEGACY_TIMEZONE.name(),
+        why
+      );
+      LEGACY_TIMEZONE
+    })
+  }
+
+  pub fn schedule(&self) -> Result<Schedule, cron::error::Error> {
+    Schedule::from_str(&self.cron_schedule)
+  }
+
+  /**
+   * The next time this job should fire after `now`, in the job's timezone
+   */
+  pub fn next_date_after(&self, now: &DateTime<Utc>) -> Option<DateTime<Tz>> {
+    let tz = self.tz();
+    self
+      .schedule()
+      .ok()
+      .and_then(|s| s.after(&now.with_timezone(&tz)).next())
+  }
+
+  /**
+   * The most recent time this job was due but never sent, as long as it
+   * falls within `grace` of `now`. Runs from before the job was created
+   * or before its last send don't count.
+   */
+  pub fn missed_date(&self, now: &DateTime<Utc>, grace: Duration) -> Option<DateTime<Tz>> {
+    let tz = self.tz();
+    let mut since = *now - grace;
+    for ts in [self.created_on_ts, self.last_sent_ts].iter().flatten() {
+      let ts = DateTime::<Utc>::from_utc(*ts, Utc);
+      if ts > since {
+        since = ts;
+      }
+    }
+
+    self
+      .schedule()
+      .ok()?
+      .after(&since.with_timezone(&tz))
+      .take_while(|d| d.with_timezone(&Utc) <= *now)
+      .last()
+  }
 }
 
 #[derive(Insertable, Debug)]
@@ -20,4 +128,321 @@ pub struct NewGotdJob {
   pub guild_id: u64,
   pub cron_schedule: String,
   pub created_by_id: u64,
+  pub timezone: String,
+  pub kind: String,
+  pub game_platform: Option<String>,
+  pub game_decade: Option<i32>,
+}
+
+#[derive(Identifiable, Queryable, Debug)]
+#[table_name = "gotd_history"]
+#[primary_key("id")]
+pub struct GotdHistory {
+  pub id: i32,
+  pub channel_id: u64,
+  pub game_id: i32,
+  pub game_guid: String,
+  pub game_name: String,
+  pub sent_on_ts: chrono::NaiveDateTime,
+  pub site_detail_url: Option<String>,
+  pub platforms: Option<String>,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "gotd_history"]
+pub struct NewGotdHistory {
+  pub channel_id: u64,
+  pub game_id: i32,
+  pub game_guid: String,
+  pub game_name: String,
+  pub site_detail_url: Option<String>,
+  pub platforms: Option<String>,
+}
+
+#[derive(Identifiable, Queryable, Debug)]
+#[table_name = "wyr_votes"]
+#[primary_key("id")]
+pub struct WyrVote {
+  pub id: i32,
+  pub wyr_id: String,
+  pub message_id: u64,
+  pub channel_id: u64,
+  pub guild_id: u64,
+  pub user_id: u64,
+  pub option_idx: i32,
+  pub voted_on_ts: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "wyr_votes"]
+pub struct NewWyrVote {
+  pub wyr_id: String,
+  pub message_id: u64,
+  pub channel_id: u64,
+  pub guild_id: u64,
+  pub user_id: u64,
+  pub option_idx: i32,
+}
+
+#[derive(Identifiable, Queryable, Debug)]
+#[table_name = "wyr_polls"]
+#[primary_key("id")]
+pub struct WyrPoll {
+  pub id: i32,
+  pub wyr_id: String,
+  pub message_id: u64,
+  pub channel_id: u64,
+  pub guild_id: u64,
+  pub created_on_ts: Option<chrono::NaiveDateTime>,
+  pub closes_on_ts: Option<chrono::NaiveDateTime>,
+  pub is_closed: bool,
+}
+
+impl WyrPoll {
+  /**
+   * Whether the poll should be closed at `now` (utc)
+   */
+  pub fn is_due(&self, now: chrono::NaiveDateTime) -> bool {
+    !self.is_closed && self.closes_on_ts.map(|ts| ts <= now).unwrap_or(false)
+  }
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "wyr_polls"]
+pub struct NewWyrPoll {
+  pub wyr_id: String,
+  pub message_id: u64,
+  pub channel_id: u64,
+  pub guild_id: u64,
+  pub closes_on_ts: Option<chrono::NaiveDateTime>,
+}
+
+pub const SUBMISSION_PENDING: &str = "pending";
+pub const SUBMISSION_APPROVED: &str = "approved";
+pub const SUBMISSION_REJECTED: &str = "rejected";
+
+// submissions share the wyr id space with the datasets, so prefix them
+pub const SUBMISSION_ID_PREFIX: &str = "sub-";
+
+#[derive(Identifiable, Queryable, Debug)]
+#[table_name = "wyr_submissions"]
+#[primary_key("id")]
+pub struct WyrSubmission {
+  pub id: i32,
+  pub guild_id: u64,
+  pub channel_id: u64,
+  pub submitted_by_id: u64,
+  pub title: String,
+  pub options: String,
+  pub status: String,
+  pub reviewed_by_id: Option<u64>,
+  pub created_on_ts: Option<chrono::NaiveDateTime>,
+  pub reviewed_on_ts: Option<chrono::NaiveDateTime>,
+}
+
+impl WyrSubmission {
+  /**
+   * The submission as a wyr poll. Options are stored as a json array.
+   */
+  pub fn to_wyr(&self) -> Option<Wyr> {
+    let options = serde_json::from_str::<Vec<String>>(&self.options).ok()?;
+    Some(Wyr {
+      id: format!("{}{}", SUBMISSION_ID_PREFIX, self.id),
+      title: self.title.clone(),
+      url: String::new(),
+      options,
+      submitted_by: Some(self.submitted_by_id),
+    })
+  }
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "wyr_submissions"]
+pub struct NewWyrSubmission {
+  pub guild_id: u64,
+  pub channel_id: u64,
+  pub submitted_by_id: u64,
+  pub title: String,
+  pub options: String,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "wyr_shown"]
+pub struct NewWyrShown {
+  pub guild_id: u64,
+  pub wyr_id: String,
+}
+
+/**
+ * What an auto-reaction rule looks for in a message
+ */
+pub const TRIGGER_EMOJI: &str = "emoji";
+pub const TRIGGER_WORD: &str = "word";
+pub const TRIGGER_REGEX: &str = "regex";
+pub const TRIGGER_USER: &str = "user";
+
+#[derive(Identifiable, Queryable, Debug, Clone)]
+#[table_name = "react_rules"]
+#[primary_key("id")]
+pub struct ReactRule {
+  pub id: i32,
+  pub guild_id: u64,
+  pub trigger_kind: String,
+  pub trigger_value: String,
+  // space separated emoji to react with
+  pub reactions: String,
+  pub created_by_id: u64,
+  pub created_on_ts: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "react_rules"]
+pub struct NewReactRule {
+  pub guild_id: u64,
+  pub trigger_kind: String,
+  pub trigger_value: String,
+  pub reactions: String,
+  pub created_by_id: u64,
+}
+
+#[derive(Identifiable, Queryable, Debug)]
+#[table_name = "game_cache"]
+#[primary_key("game_id")]
+pub struct CachedGame {
+  pub game_id: i32,
+  // the game as serde_json wrote it
+  pub game_json: String,
+  pub cached_on_ts: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "game_cache"]
+pub struct NewCachedGame {
+  pub game_id: i32,
+  pub game_json: String,
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chrono::TimeZone;
+
+  fn job(cron_schedule: &str, timezone: &str) -> GotdJob {
+    GotdJob {
+      id: 1,
+      channel_id: 1,
+      guild_id: 1,
+      cron_schedule: String::from(cron_schedule),
+      created_on_ts: None,
+      created_by_id: 1,
+      is_deleted: false,
+      timezone: String::from(timezone),
+      last_sent_ts: None,
+      kind: String::from(JOB_KIND_GOTD),
+      game_platform: None,
+      game_decade: None,
+    }
+  }
+
+  #[test]
+  fn tz_falls_back_to_default_for_unknown_zones() {
+    assert_eq!(
+      job("0 0 8 * * * *", "Mars/Olympus_Mons").tz(),
+      LEGACY_TIMEZONE
+    );
+  }
+
+  #[test]
+  fn next_date_after_respects_daylight_saving() {
+    let summer = Utc.ymd(2021, 7, 1).and_hms(0, 0, 0);
+    let winter = Utc.ymd(2021, 12, 1).and_hms(0, 0, 0);
+    let j = job("0 0 8 * * * *", "America/New_York");
+
+    let s = j.next_date_after(&summer).unwrap();
+    let w = j.next_date_after(&winter).unwrap();
+    assert_eq!(
+      s.with_timezone(&Utc).to_rfc3339(),
+      "2021-07-01T12:00:00+00:00"
+    );
+    assert_eq!(
+      w.with_timezone(&Utc).to_rfc3339(),
+      "2021-12-01T13:00:00+00:00"
+    );
+  }
+
+  #[test]
+  fn next_date_after_uses_job_timezone() {
+    let now = Utc.ymd(2021, 7, 1).and_hms(0, 0, 0);
+    let j = job("0 0 8 * * * *", "Europe/London");
+    let next = j.next_date_after(&now).unwrap();
+    assert_eq!(
+      next.with_timezone(&Utc).to_rfc3339(),
+      "2021-07-01T07:00:00+00:00"
+    );
+  }
+
+  #[test]
+  fn missed_date_finds_run_inside_grace_window() {
+    let now = Utc.ymd(2021, 7, 1).and_hms(12, 30, 0);
+    let j = job("0 0 8 * * * *", "America/New_York");
+
+    let missed = j.missed_date(&now, Duration::hours(1)).unwrap();
+    assert_eq!(
+      missed.with_timezone(&Utc).to_rfc3339(),
+      "2021-07-01T12:00:00+00:00"
+    );
+    assert!(j.missed_date(&now, Duration::minutes(15)).is_none());
+  }
+
+  #[test]
+  fn missed_date_ignores_runs_already_sent() {
+    let now = Utc.ymd(2021, 7, 1).and_hms(12, 30, 0);
+    let mut j = job("0 0 8 * * * *", "America/New_York");
+    j.last_sent_ts = Some(Utc.ymd(2021, 7, 1).and_hms(12, 0, 1).naive_utc());
+
+    assert!(j.missed_date(&now, Duration::hours(1)).is_none());
+  }
+
+  #[test]
+  fn submission_to_wyr_parses_options() {
+    let sub = WyrSubmission {
+      id: 7,
+      guild_id: 1,
+      channel_id: 1,
+      submitted_by_id: 42,
+      title: String::from("WYR?"),
+      options: String::from(r#"["Red","Orange"]"#),
+      status: String::from(SUBMISSION_APPROVED),
+      reviewed_by_id: None,
+      created_on_ts: None,
+      reviewed_on_ts: None,
+    };
+
+    let wyr = sub.to_wyr().unwrap();
+    assert_eq!(wyr.id, "sub-7");
+    assert_eq!(wyr.options, vec!["Red", "Orange"]);
+    assert_eq!(wyr.submitted_by, Some(42));
+  }
+
+  #[test]
+  fn game_filter_reads_stored_columns() {
+    let mut j = job("0 0 8 * * * *", "UTC");
+    assert!(j.game_filter().unwrap().is_empty());
+
+    j.game_platform = Some(String::from("snes"));
+    j.game_decade = Some(1990);
+    let filter = j.game_filter().unwrap();
+    assert_eq!(filter.platform.map(|p| p.id), Some(9));
+    assert_eq!(filter.decade, Some(1990));
+
+    j.game_platform = Some(String::from("virtual-boy"));
+    assert!(j.game_filter().is_err());
+  }
+
+  #[test]
+  fn job_kind_label_defaults_to_gotd() {
+    assert_eq!(job_kind_label(JOB_KIND_WYR), "Would You Rather");
+    assert_eq!(job_kind_label(JOB_KIND_GOTD), "Game of the Day");
+    assert_eq!(job_kind_label("mystery"), "Game of the Day");
+  }
 }
@@ -1,6 +1,15 @@
-use super::model::{GotdJob, NewGotdJob};
-use super::schema::gotd_schedules::dsl::{channel_id, gotd_schedules, guild_id, id, is_deleted};
-use super::storage::GotdDb;
+use super::model::{
+  CachedGame, GotdHistory, GotdJob, NewCachedGame, NewGotdHistory, NewGotdJob, NewReactRule,
+  NewWyrPoll, NewWyrShown, NewWyrSubmission, NewWyrVote, ReactRule, WyrPoll, WyrSubmission,
+  WyrVote, SUBMISSION_APPROVED, SUBMISSION_PENDING,
+};
+use super::schema::gotd_schedules::dsl::{
+  channel_id, gotd_schedules, guild_id, id, is_deleted, last_sent_ts,
+};
+use super::schema::{
+  game_cache, gotd_history, react_rules, wyr_polls, wyr_shown, wyr_submissions, wyr_votes,
+};
+use super::storage::{GameCacheDb, GotdDb, ReactDb, WyrDb};
 use diesel::prelude::*;
 use diesel::r2d2::{ConnectionManager, Pool};
 use diesel::MysqlConnection;
@@ -36,57 +45,18 @@ impl GotdDb for GotdMysqlStore {
   }
 
   /**
-   * Check whether or not the current channel has an active sched or not
-   * will also return false if there is no saved sched for this channel
+   * Get every active sched for the channel, oldest first. A channel
+   * can have any number of them.
    */
-  fn has_active_sched(&self, channel: u64) -> Result<bool, Box<dyn Error + Send + Sync>> {
+  fn get_active_sched(&self, channel: u64) -> Result<Vec<GotdJob>, Box<dyn Error + Send + Sync>> {
     let conn = self.db.get()?;
     let results = gotd_schedules
       .filter(is_deleted.eq(false))
       .filter(channel_id.eq(channel))
-      .limit(1)
+      .order(id.asc())
       .load::<GotdJob>(&conn)?;
 
-    Ok(!results.is_empty())
-  }
-
-  /**
-   * If an active sched exists for channel, return it, otherwise
-   * return an empty Option
-   */
-  fn get_active_sched(
-    &self,
-    channel: u64,
-  ) -> Result<Option<GotdJob>, Box<dyn Error + Send + Sync>> {
-    let conn = self.db.get()?;
-    let results = gotd_schedules
-      .filter(is_deleted.eq(false))
-      .filter(channel_id.eq(channel))
-      .limit(1)
-      .load::<GotdJob>(&conn)?;
-
-    if results.is_empty() {
-      return Ok(None);
-    }
-
-    // because GotdJob doesn't implement the Copy/Clone traits
-    // and I don't know why it can't (tried using derive(copy, clone))
-    // then just map over the struct myself
-    // alternatively I could try to return a borrowed reference
-    // but then I think I'd have to mess with struct lifetimes and
-    // I don't really want to do that...
-    match results.get(0) {
-      Some(g) => Ok(Some(GotdJob {
-        id: g.id,
-        channel_id: g.channel_id,
-        guild_id: g.guild_id,
-        cron_schedule: g.cron_schedule.clone(),
-        created_on_ts: g.created_on_ts,
-        created_by_id: g.created_by_id,
-        is_deleted: g.is_deleted,
-      })),
-      None => Ok(None),
-    }
+    Ok(results)
   }
 
   fn get_all_active_sched_for_guild(
@@ -116,6 +86,22 @@ impl GotdDb for GotdMysqlStore {
     }
   }
 
+  /**
+   * Record when the sched identified by id last sent successfully
+   */
+  fn set_last_sent(
+    &self,
+    job_id: i32,
+    sent_on: chrono::NaiveDateTime,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let conn = self.db.get()?;
+    diesel::update(gotd_schedules.filter(id.eq(job_id)))
+      .set(last_sent_ts.eq(Some(sent_on)))
+      .execute(&conn)?;
+
+    Ok(())
+  }
+
   /**
    * Get all active sched
    */
@@ -127,4 +113,403 @@ impl GotdDb for GotdMysqlStore {
 
     Ok(results)
   }
+
+  /**
+   * Remember that a game was posted to a channel
+   */
+  fn save_history(&self, entry: NewGotdHistory) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let conn = self.db.get()?;
+    diesel::insert_into(gotd_history::table)
+      .values(&entry)
+      .execute(&conn)?;
+
+    Ok(())
+  }
+
+  /**
+   * Check whether a game was posted to the channel at or after `since`
+   */
+  fn was_sent_since(
+    &self,
+    channel: u64,
+    game: i32,
+    since: chrono::NaiveDateTime,
+  ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let conn = self.db.get()?;
+    let results = gotd_history::table
+      .filter(gotd_history::channel_id.eq(channel))
+      .filter(gotd_history::game_id.eq(game))
+      .filter(gotd_history::sent_on_ts.ge(since))
+      .select(gotd_history::id)
+      .limit(1)
+      .load::<i32>(&conn)?;
+
+    Ok(!results.is_empty())
+  }
+
+  /**
+   * Get a page of games posted to the channel, newest first
+   */
+  fn get_history(
+    &self,
+    channel: u64,
+    offset: i64,
+    limit: i64,
+  ) -> Result<Vec<GotdHistory>, Box<dyn Error + Send + Sync>> {
+    let conn = self.db.get()?;
+    let results = gotd_history::table
+      .filter(gotd_history::channel_id.eq(channel))
+      .order(gotd_history::sent_on_ts.desc())
+      .offset(offset)
+      .limit(limit)
+      .load::<GotdHistory>(&conn)?;
+
+    Ok(results)
+  }
+
+  /**
+   * Count every game posted to the channel
+   */
+  fn count_history(&self, channel: u64) -> Result<i64, Box<dyn Error + Send + Sync>> {
+    let conn = self.db.get()?;
+    let count = gotd_history::table
+      .filter(gotd_history::channel_id.eq(channel))
+      .count()
+      .get_result::<i64>(&conn)?;
+
+    Ok(count)
+  }
+}
+
+impl WyrDb for GotdMysqlStore {
+  /**
+   * Save a vote on a wyr poll. A user only gets one vote per poll
+   * message, so voting again replaces their previous choice.
+   */
+  fn save_vote(&self, vote: NewWyrVote) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let conn = self.db.get()?;
+    // (wyr_id, message_id, user_id) is a unique key, so REPLACE swaps out the old vote
+    diesel::replace_into(wyr_votes::table)
+      .values(&vote)
+      .execute(&conn)?;
+
+    Ok(())
+  }
+
+  /**
+   * Get every vote cast on the poll message
+   */
+  fn get_votes(&self, message: u64) -> Result<Vec<WyrVote>, Box<dyn Error + Send + Sync>> {
+    let conn = self.db.get()?;
+    let results = wyr_votes::table
+      .filter(wyr_votes::message_id.eq(message))
+      .load::<WyrVote>(&conn)?;
+
+    Ok(results)
+  }
+
+  /**
+   * Get every vote cast on any poll in the guild
+   */
+  fn get_guild_votes(&self, guild: u64) -> Result<Vec<WyrVote>, Box<dyn Error + Send + Sync>> {
+    let conn = self.db.get()?;
+    let results = wyr_votes::table
+      .filter(wyr_votes::guild_id.eq(guild))
+      .load::<WyrVote>(&conn)?;
+
+    Ok(results)
+  }
+
+  /**
+   * Remember a posted wyr poll
+   */
+  fn save_poll(&self, poll: NewWyrPoll) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let conn = self.db.get()?;
+    diesel::insert_into(wyr_polls::table)
+      .values(&poll)
+      .execute(&conn)?;
+
+    Ok(())
+  }
+
+  /**
+   * Get the poll posted as the message, if there is one
+   */
+  fn get_poll(&self, message: u64) -> Result<Option<WyrPoll>, Box<dyn Error + Send + Sync>> {
+    let conn = self.db.get()?;
+    let result = wyr_polls::table
+      .filter(wyr_polls::message_id.eq(message))
+      .first::<WyrPoll>(&conn)
+      .optional()?;
+
+    Ok(result)
+  }
+
+  /**
+   * Get every poll that is still open and has a closing time
+   */
+  fn get_open_polls(&self) -> Result<Vec<WyrPoll>, Box<dyn Error + Send + Sync>> {
+    let conn = self.db.get()?;
+    let results = wyr_polls::table
+      .filter(wyr_polls::is_closed.eq(false))
+      .filter(wyr_polls::closes_on_ts.is_not_null())
+      .load::<WyrPoll>(&conn)?;
+
+    Ok(results)
+  }
+
+  /**
+   * Mark the poll identified by id closed. Return true if it was open.
+   */
+  fn close_poll(&self, poll_id: i32) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let conn = self.db.get()?;
+    let num_updated = diesel::update(
+      wyr_polls::table
+        .filter(wyr_polls::id.eq(poll_id))
+        .filter(wyr_polls::is_closed.eq(false)),
+    )
+    .set(wyr_polls::is_closed.eq(true))
+    .execute(&conn)?;
+
+    Ok(num_updated == 1)
+  }
+
+  /**
+   * Save a member's wyr submission as pending and return it
+   */
+  fn save_submission(
+    &self,
+    submission: NewWyrSubmission,
+  ) -> Result<WyrSubmission, Box<dyn Error + Send + Sync>> {
+    let conn = self.db.get()?;
+    // mysql won't hand back the new row on insert, so read it back in the same transaction
+    let saved = conn.transaction::<_, diesel::result::Error, _>(|| {
+      diesel::insert_into(wyr_submissions::table)
+        .values(&submission)
+        .execute(&conn)?;
+
+      wyr_submissions::table
+        .filter(wyr_submissions::guild_id.eq(submission.guild_id))
+        .filter(wyr_submissions::submitted_by_id.eq(submission.submitted_by_id))
+        .order(wyr_submissions::id.desc())
+        .first::<WyrSubmission>(&conn)
+    })?;
+
+    Ok(saved)
+  }
+
+  /**
+   * Get the submission identified by id, if there is one
+   */
+  fn get_submission(
+    &self,
+    submission_id: i32,
+  ) -> Result<Option<WyrSubmission>, Box<dyn Error + Send + Sync>> {
+    let conn = self.db.get()?;
+    let result = wyr_submissions::table
+      .filter(wyr_submissions::id.eq(submission_id))
+      .first::<WyrSubmission>(&conn)
+      .optional()?;
+
+    Ok(result)
+  }
+
+  /**
+   * Approve or reject a pending submission. Return true if it was pending.
+   */
+  fn review_submission(
+    &self,
+    submission_id: i32,
+    new_status: &str,
+    reviewer: u64,
+  ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let conn = self.db.get()?;
+    let num_updated = diesel::update(
+      wyr_submissions::table
+        .filter(wyr_submissions::id.eq(submission_id))
+        .filter(wyr_submissions::status.eq(SUBMISSION_PENDING)),
+    )
+    .set((
+      wyr_submissions::status.eq(new_status),
+      wyr_submissions::reviewed_by_id.eq(Some(reviewer)),
+      wyr_submissions::reviewed_on_ts.eq(Some(chrono::Utc::now().naive_utc())),
+    ))
+    .execute(&conn)?;
+
+    Ok(num_updated == 1)
+  }
+
+  /**
+   * Get every approved submission in the guild
+   */
+  fn get_approved_submissions(
+    &self,
+    guild: u64,
+  ) -> Result<Vec<WyrSubmission>, Box<dyn Error + Send + Sync>> {
+    let conn = self.db.get()?;
+    let results = wyr_submissions::table
+      .filter(wyr_submissions::guild_id.eq(guild))
+      .filter(wyr_submissions::status.eq(SUBMISSION_APPROVED))
+      .load::<WyrSubmission>(&conn)?;
+
+    Ok(results)
+  }
+
+  /**
+   * Get the ids of every wyr already shown in the guild
+   */
+  fn get_shown_wyr(&self, guild: u64) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+    let conn = self.db.get()?;
+    let results = wyr_shown::table
+      .filter(wyr_shown::guild_id.eq(guild))
+      // showing a wyr again replaces its row, so the newest id is the latest
+      .order(wyr_shown::id.asc())
+      .select(wyr_shown::wyr_id)
+      .load::<String>(&conn)?;
+
+    Ok(results)
+  }
+
+  /**
+   * Remember that a wyr was shown in the guild
+   */
+  fn mark_wyr_shown(&self, guild: u64, wyr: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let conn = self.db.get()?;
+    // (guild_id, wyr_id) is a unique key, so showing it again just refreshes the row
+    diesel::replace_into(wyr_shown::table)
+      .values(&NewWyrShown {
+        guild_id: guild,
+        wyr_id: String::from(wyr),
+      })
+      .execute(&conn)?;
+
+    Ok(())
+  }
+
+  /**
+   * Forget that the wyrs were shown in the guild so they can come up again
+   */
+  fn reset_shown_wyr(
+    &self,
+    guild: u64,
+    wyr_ids: &[String],
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let conn = self.db.get()?;
+    diesel::delete(
+      wyr_shown::table
+        .filter(wyr_shown::guild_id.eq(guild))
+        .filter(wyr_shown::wyr_id.eq_any(wyr_ids)),
+    )
+    .execute(&conn)?;
+
+    Ok(())
+  }
+}
+
+impl ReactDb for GotdMysqlStore {
+  /**
+   * Save a new auto-reaction rule for a guild
+   */
+  fn save_react_rule(&self, rule: NewReactRule) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let conn = self.db.get()?;
+    diesel::insert_into(react_rules::table)
+      .values(&rule)
+      .execute(&conn)?;
+
+    Ok(())
+  }
+
+  /**
+   * Get every auto-reaction rule in the guild, oldest first
+   */
+  fn get_react_rules(&self, guild: u64) -> Result<Vec<ReactRule>, Box<dyn Error + Send + Sync>> {
+    let conn = self.db.get()?;
+    let results = react_rules::table
+      .filter(react_rules::guild_id.eq(guild))
+      .order(react_rules::id.asc())
+      .load::<ReactRule>(&conn)?;
+
+    Ok(results)
+  }
+
+  /**
+   * Get every auto-reaction rule in every guild
+   */
+  fn get_all_react_rules(&self) -> Result<Vec<ReactRule>, Box<dyn Error + Send + Sync>> {
+    let conn = self.db.get()?;
+    let results = react_rules::table
+      .order(react_rules::id.asc())
+      .load::<ReactRule>(&conn)?;
+
+    Ok(results)
+  }
+
+  /**
+   * Delete the guild's rule identified by id. Return true if it existed.
+   */
+  fn delete_react_rule(&self, guild: u64, rule: i32) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let conn = self.db.get()?;
+    let deleted = diesel::delete(
+      react_rules::table
+        .filter(react_rules::guild_id.eq(guild))
+        .filter(react_rules::id.eq(rule)),
+    )
+    .execute(&conn)?;
+
+    Ok(deleted > 0)
+  }
+}
+
+impl GameCacheDb for GotdMysqlStore {
+  /**
+   * Save a game, replacing whatever was cached under its id
+   */
+  fn cache_game(&self, game: NewCachedGame) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let conn = self.db.get()?;
+    diesel::replace_into(game_cache::table)
+      .values(&game)
+      .execute(&conn)?;
+
+    Ok(())
+  }
+
+  /**
+   * Get the cached game with the GiantBomb id, if there is one
+   */
+  fn get_cached_game(&self, game: i32) -> Result<Option<CachedGame>, Box<dyn Error + Send + Sync>> {
+    let conn = self.db.get()?;
+    let result = game_cache::table
+      .filter(game_cache::game_id.eq(game))
+      .first::<CachedGame>(&conn)
+      .optional()?;
+
+    Ok(result)
+  }
+
+  /**
+   * Get the cached game at offset when ordered by id, if there is one
+   */
+  fn get_cached_game_at(
+    &self,
+    offset: i64,
+  ) -> Result<Option<CachedGame>, Box<dyn Error + Send + Sync>> {
+    let conn = self.db.get()?;
+    let result = game_cache::table
+      .order(game_cache::game_id.asc())
+      .offset(offset)
+      .first::<CachedGame>(&conn)
+      .optional()?;
+
+    Ok(result)
+  }
+
+  /**
+   * Count every cached game
+   */
+  fn count_cached_games(&self) -> Result<i64, Box<dyn Error + Send + Sync>> {
+    let conn = self.db.get()?;
+    let count = game_cache::table.count().get_result::<i64>(&conn)?;
+
+    Ok(count)
+  }
 }
@@ -0,0 +1,301 @@
+use super::model::{ReactRule, TRIGGER_EMOJI, TRIGGER_REGEX, TRIGGER_USER, TRIGGER_WORD};
+use regex::{Regex, RegexBuilder};
+use serenity::model::channel::ReactionType;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use tracing::{info, warn};
+
+// discord shows at most 20 reactions on a message, keep each rule to a few
+const MAX_REACTIONS: usize = 5;
+// keeps a member from handing us a regex that takes forever to compile
+const REGEX_SIZE_LIMIT: usize = 1 << 16;
+
+// the bot has always reacted to Kevin Toms with Kevin Toms. The ids are
+// negative so they never share a cooldown with a saved rule.
+const DEFAULT_RULES: &[(i32, &str)] = &[
+  (-1, "<:KevinToms:776453874310709249>"),
+  (-2, "<:KevinToms:885148040619511808>"),
+];
+
+#[derive(Debug)]
+pub enum Trigger {
+  Emoji(String),
+  Word(String),
+  Regex(Regex),
+  User(u64),
+}
+
+/**
+ * A react rule checked and ready to run against messages
+ */
+#[derive(Debug)]
+pub struct Rule {
+  pub id: i32,
+  pub trigger: Trigger,
+  pub reactions: Vec<ReactionType>,
+}
+
+impl Rule {
+  /**
+   * Build a rule from what a member typed into /react-rule add
+   */
+  pub fn parse(id: i32, kind: &str, value: &str, reactions: &str) -> Result<Self, String> {
+    let value = value.trim();
+    if value.is_empty() {
+      return Err(String::from("The trigger can't be empty."));
+    }
+
+    let trigger = match kind {
+      TRIGGER_EMOJI => Trigger::Emoji(String::from(value)),
+      TRIGGER_WORD => {
+        if !value.chars().all(|c| c.is_alphanumeric()) {
+          return Err(format!(
+            "`{}` isn't a single word. Use a regex to match phrases.",
+            value
+          ));
+        }
+        Trigger::Word(value.to_lowercase())
+      }
+      TRIGGER_REGEX => RegexBuilder::new(value)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .build()
+        .map(Trigger::Regex)
+        .map_err(|e| format!("That regex doesn't work: {}", e))?,
+      TRIGGER_USER => {
+        // accept a raw id as well as a <@id> or <@!id> mention
+        let digits = value
+          .trim_start_matches("<@")
+          .trim_start_matches('!')
+          .trim_end_matches('>');
+        Trigger::User(
+          digits
+            .parse::<u64>()
+            .map_err(|_| format!("`{}` isn't a user mention or id.", value))?,
+        )
+      }
+      k => return Err(format!("Unknown trigger `{}`.", k)),
+    };
+
+    Ok(Self {
+      id,
+      trigger,
+      reactions: parse_reactions(reactions)?,
+    })
+  }
+
+  /**
+   * Whether the message sent by author should get this rule's reactions
+   */
+  pub fn matches(&self, author_id: u64, content: &str) -> bool {
+    match &self.trigger {
+      Trigger::Emoji(e) => content.contains(e.as_str()),
+      Trigger::Word(w) => content
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|t| t.to_lowercase() == *w),
+      Trigger::Regex(r) => r.is_match(content),
+      Trigger::User(u) => *u == author_id,
+    }
+  }
+}
+
+impl TryFrom<&ReactRule> for Rule {
+  type Error = String;
+
+  fn try_from(rule: &ReactRule) -> Result<Self, Self::Error> {
+    Rule::parse(
+      rule.id,
+      &rule.trigger_kind,
+      &rule.trigger_value,
+      &rule.reactions,
+    )
+  }
+}
+
+/**
+ * Split space separated emoji into reactions. Custom emoji look like
+ * <:name:id>, anything else has to at least not be plain text.
+ */
+pub fn parse_reactions(reactions: &str) -> Result<Vec<ReactionType>, String> {
+  let parsed = reactions
+    .split_whitespace()
+    .map(|r| match ReactionType::from_str(r) {
+      Ok(ReactionType::Unicode(u)) if u.is_ascii() => Err(format!("`{}` isn't an emoji.", r)),
+      Ok(reaction) => Ok(reaction),
+      Err(_) => Err(format!("`{}` isn't an emoji.", r)),
+    })
+    .collect::<Result<Vec<ReactionType>, String>>()?;
+
+  if parsed.is_empty() {
+    return Err(String::from("Give at least one emoji to react with."));
+  }
+  if parsed.len() > MAX_REACTIONS {
+    return Err(format!(
+      "A rule can react with at most {} emoji.",
+      MAX_REACTIONS
+    ));
+  }
+  Ok(parsed)
+}
+
+/**
+ * Every guild's react rules, kept in memory so checking a message doesn't
+ * touch the database. Commands that change rules replace the guild's set.
+ * Guilds without any rules of their own, and DMs, get the default rules.
+ */
+pub struct ReactStore {
+  guilds: RwLock<HashMap<u64, Vec<Arc<Rule>>>>,
+  defaults: Vec<Arc<Rule>>,
+}
+
+impl ReactStore {
+  /**
+   * Build the store from every saved rule. Rules that no longer parse are
+   * left out with a warning rather than stopping the bot.
+   */
+  pub fn new(rules: &[ReactRule]) -> Self {
+    let mut guilds = HashMap::<u64, Vec<Arc<Rule>>>::new();
+    for rule in rules {
+      if let Some(r) = compile(rule) {
+        guilds.entry(rule.guild_id).or_default().push(Arc::new(r));
+      }
+    }
+
+    info!(
+      "Loaded {} react rule(s) for {} guild(s)",
+      guilds.values().map(|g| g.len()).sum::<usize>(),
+      guilds.len()
+    );
+    Self {
+      guilds: RwLock::new(guilds),
+      defaults: default_rules(),
+    }
+  }
+
+  /**
+   * Swap in the guild's rules after they change
+   */
+  pub fn set_guild(&self, guild_id: u64, rules: &[ReactRule]) {
+    let compiled = rules
+      .iter()
+      .filter_map(compile)
+      .map(Arc::new)
+      .collect::<Vec<Arc<Rule>>>();
+
+    let mut guilds = self.guilds.write().unwrap();
+    if compiled.is_empty() {
+      guilds.remove(&guild_id);
+    } else {
+      guilds.insert(guild_id, compiled);
+    }
+  }
+
+  /**
+   * Every rule in the guild that the message triggers
+   */
+  pub fn matching(&self, guild_id: u64, author_id: u64, content: &str) -> Vec<Arc<Rule>> {
+    let guilds = self.guilds.read().unwrap();
+    guilds
+      .get(&guild_id)
+      .unwrap_or(&self.defaults)
+      .iter()
+      .filter(|r| r.matches(author_id, content))
+      .cloned()
+      .collect()
+  }
+}
+
+fn default_rules() -> Vec<Arc<Rule>> {
+  DEFAULT_RULES
+    .iter()
+    .map(|(id, emoji)| {
+      Arc::new(
+        Rule::parse(*id, TRIGGER_EMOJI, emoji, emoji).expect("default react rule is invalid"),
+      )
+    })
+    .collect()
+}
+
+fn compile(rule: &ReactRule) -> Option<Rule> {
+  match Rule::try_from(rule) {
+    Ok(r) => Some(r),
+    Err(why) => {
+      warn!("Skipping react rule {}: {}", rule.id, why);
+      None
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const KEVIN_TOMS: &str = "<:KevinToms:776453874310709249>";
+
+  #[test]
+  fn emoji_trigger_matches_anywhere() {
+    let rule = Rule::parse(1, TRIGGER_EMOJI, KEVIN_TOMS, KEVIN_TOMS).unwrap();
+    assert!(rule.matches(1, &format!("look {} here", KEVIN_TOMS)));
+    assert!(!rule.matches(1, "<:KevinToms:885148040619511808>"));
+  }
+
+  #[test]
+  fn word_trigger_matches_whole_words() {
+    let rule = Rule::parse(1, TRIGGER_WORD, "Taco", "🌮").unwrap();
+    assert!(rule.matches(1, "who wants TACO, tonight?"));
+    assert!(!rule.matches(1, "tacos are better"));
+    assert!(Rule::parse(1, TRIGGER_WORD, "taco tuesday", "🌮").is_err());
+  }
+
+  #[test]
+  fn regex_and_user_triggers() {
+    let rule = Rule::parse(1, TRIGGER_REGEX, r"(?i)^good (morning|night)", "👋").unwrap();
+    assert!(rule.matches(1, "Good morning all"));
+    assert!(!rule.matches(1, "not good morning"));
+    assert!(Rule::parse(1, TRIGGER_REGEX, "(unclosed", "👋").is_err());
+
+    let rule = Rule::parse(1, TRIGGER_USER, "<@!42>", "👀").unwrap();
+    assert!(rule.matches(42, "anything"));
+    assert!(!rule.matches(43, "anything"));
+  }
+
+  fn rule(guild_id: u64) -> ReactRule {
+    ReactRule {
+      id: 7,
+      guild_id,
+      trigger_kind: String::from(TRIGGER_WORD),
+      trigger_value: String::from("taco"),
+      reactions: String::from("🌮"),
+      created_by_id: 1,
+      created_on_ts: None,
+    }
+  }
+
+  #[test]
+  fn guilds_without_rules_get_the_defaults() {
+    let store = ReactStore::new(&[rule(1)]);
+    let kevin = format!("hi {}", KEVIN_TOMS);
+
+    assert_eq!(store.matching(2, 1, &kevin).len(), 1);
+    assert_eq!(store.matching(0, 1, &kevin).len(), 1);
+    assert!(store.matching(1, 1, &kevin).is_empty());
+    assert_eq!(store.matching(1, 1, "taco time").len(), 1);
+
+    store.set_guild(1, &[]);
+    assert_eq!(store.matching(1, 1, &kevin).len(), 1);
+  }
+
+  #[test]
+  fn parse_reactions_rejects_text() {
+    assert_eq!(
+      parse_reactions(&format!("🌮 {}", KEVIN_TOMS))
+        .unwrap()
+        .len(),
+      2
+    );
+    assert!(parse_reactions("lol").is_err());
+    assert!(parse_reactions("").is_err());
+    assert!(parse_reactions("🌮 🌮 🌮 🌮 🌮 🌮").is_err());
+  }
+}
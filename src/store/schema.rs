@@ -1,3 +1,24 @@
+table! {
+    game_cache (game_id) {
+        game_id -> Integer,
+        game_json -> Text,
+        cached_on_ts -> Timestamp,
+    }
+}
+
+table! {
+    gotd_history (id) {
+        id -> Integer,
+        channel_id -> Unsigned<Bigint>,
+        game_id -> Integer,
+        game_guid -> Varchar,
+        game_name -> Varchar,
+        sent_on_ts -> Timestamp,
+        site_detail_url -> Nullable<Varchar>,
+        platforms -> Nullable<Varchar>,
+    }
+}
+
 table! {
     gotd_schedules (id) {
         id -> Integer,
@@ -7,5 +28,83 @@ table! {
         created_on_ts -> Nullable<Timestamp>,
         created_by_id -> Unsigned<Bigint>,
         is_deleted -> Bool,
+        timezone -> Varchar,
+        last_sent_ts -> Nullable<Timestamp>,
+        kind -> Varchar,
+        game_platform -> Nullable<Varchar>,
+        game_decade -> Nullable<Integer>,
+    }
+}
+
+table! {
+    react_rules (id) {
+        id -> Integer,
+        guild_id -> Unsigned<Bigint>,
+        trigger_kind -> Varchar,
+        trigger_value -> Varchar,
+        reactions -> Varchar,
+        created_by_id -> Unsigned<Bigint>,
+        created_on_ts -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    wyr_polls (id) {
+        id -> Integer,
+        wyr_id -> Varchar,
+        message_id -> Unsigned<Bigint>,
+        channel_id -> Unsigned<Bigint>,
+        guild_id -> Unsigned<Bigint>,
+        created_on_ts -> Nullable<Timestamp>,
+        closes_on_ts -> Nullable<Timestamp>,
+        is_closed -> Bool,
+    }
+}
+
+table! {
+    wyr_shown (id) {
+        id -> Integer,
+        guild_id -> Unsigned<Bigint>,
+        wyr_id -> Varchar,
+        shown_on_ts -> Timestamp,
+    }
+}
+
+table! {
+    wyr_submissions (id) {
+        id -> Integer,
+        guild_id -> Unsigned<Bigint>,
+        channel_id -> Unsigned<Bigint>,
+        submitted_by_id -> Unsigned<Bigint>,
+        title -> Varchar,
+        options -> Text,
+        status -> Varchar,
+        reviewed_by_id -> Nullable<Unsigned<Bigint>>,
+        created_on_ts -> Nullable<Timestamp>,
+        reviewed_on_ts -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    wyr_votes (id) {
+        id -> Integer,
+        wyr_id -> Varchar,
+        message_id -> Unsigned<Bigint>,
+        channel_id -> Unsigned<Bigint>,
+        guild_id -> Unsigned<Bigint>,
+        user_id -> Unsigned<Bigint>,
+        option_idx -> Integer,
+        voted_on_ts -> Timestamp,
     }
 }
+
+allow_tables_to_appear_in_same_query!(
+    game_cache,
+    gotd_history,
+    gotd_schedules,
+    react_rules,
+    wyr_polls,
+    wyr_shown,
+    wyr_submissions,
+    wyr_votes,
+);
@@ -1,4 +1,7 @@
-use super::model::{GotdJob, NewGotdJob};
+use super::model::{
+  CachedGame, GotdHistory, GotdJob, NewCachedGame, NewGotdHistory, NewGotdJob, NewReactRule,
+  NewWyrPoll, NewWyrSubmission, NewWyrVote, ReactRule, WyrPoll, WyrSubmission, WyrVote,
+};
 use std::error::Error;
 
 pub trait GotdDb {
@@ -8,23 +11,14 @@ pub trait GotdDb {
   fn save_sched(&self, job: NewGotdJob) -> Result<(), Box<dyn Error + Send + Sync>>;
 
   /**
-   * Check whether or not the current channel has an active sched or not
-   * will also return false if there is no saved sched for this channel
+   * Get every active sched for the channel, oldest first. A channel
+   * can have any number of them.
    */
-  fn has_active_sched(&self, channel_id: u64) -> Result<bool, Box<dyn Error + Send + Sync>>;
+  fn get_active_sched(&self, channel_id: u64)
+    -> Result<Vec<GotdJob>, Box<dyn Error + Send + Sync>>;
 
   /**
-   * If an active sched exists for channel, return it, otherwise
-   * return an empty Option
-   */
-  fn get_active_sched(
-    &self,
-    channel_id: u64,
-  ) -> Result<Option<GotdJob>, Box<dyn Error + Send + Sync>>;
-
-  /**
-   * If an active sched exists for guild, return it, otherwise
-   * return an empty Option
+   * Get every active sched in the guild
    */
   fn get_all_active_sched_for_guild(
     &self,
@@ -40,4 +34,184 @@ pub trait GotdDb {
    * Delete the sched identified by id. Return true if delete is sucessful.
    */
   fn delete_sched(&self, id: i32) -> Result<bool, Box<dyn Error + Send + Sync>>;
+
+  /**
+   * Record when the sched identified by id last sent successfully
+   */
+  fn set_last_sent(
+    &self,
+    id: i32,
+    sent_on: chrono::NaiveDateTime,
+  ) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+  /**
+   * Remember that a game was posted to a channel
+   */
+  fn save_history(&self, entry: NewGotdHistory) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+  /**
+   * Check whether a game was posted to the channel at or after `since`
+   */
+  fn was_sent_since(
+    &self,
+    channel_id: u64,
+    game_id: i32,
+    since: chrono::NaiveDateTime,
+  ) -> Result<bool, Box<dyn Error + Send + Sync>>;
+
+  /**
+   * Get a page of games posted to the channel, newest first
+   */
+  fn get_history(
+    &self,
+    channel_id: u64,
+    offset: i64,
+    limit: i64,
+  ) -> Result<Vec<GotdHistory>, Box<dyn Error + Send + Sync>>;
+
+  /**
+   * Count every game posted to the channel
+   */
+  fn count_history(&self, channel_id: u64) -> Result<i64, Box<dyn Error + Send + Sync>>;
+}
+
+pub trait WyrDb {
+  /**
+   * Save a vote on a wyr poll. A user only gets one vote per poll
+   * message, so voting again replaces their previous choice.
+   */
+  fn save_vote(&self, vote: NewWyrVote) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+  /**
+   * Get every vote cast on the poll message
+   */
+  fn get_votes(&self, message_id: u64) -> Result<Vec<WyrVote>, Box<dyn Error + Send + Sync>>;
+
+  /**
+   * Get every vote cast on any poll in the guild
+   */
+  fn get_guild_votes(&self, guild_id: u64) -> Result<Vec<WyrVote>, Box<dyn Error + Send + Sync>>;
+
+  /**
+   * Remember a posted wyr poll
+   */
+  fn save_poll(&self, poll: NewWyrPoll) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+  /**
+   * Get the poll posted as the message, if there is one
+   */
+  fn get_poll(&self, message_id: u64) -> Result<Option<WyrPoll>, Box<dyn Error + Send + Sync>>;
+
+  /**
+   * Get every poll that is still open and has a closing time
+   */
+  fn get_open_polls(&self) -> Result<Vec<WyrPoll>, Box<dyn Error + Send + Sync>>;
+
+  /**
+   * Mark the poll identified by id closed. Return true if it was open.
+   */
+  fn close_poll(&self, id: i32) -> Result<bool, Box<dyn Error + Send + Sync>>;
+
+  /**
+   * Save a member's wyr submission as pending and return it
+   */
+  fn save_submission(
+    &self,
+    submission: NewWyrSubmission,
+  ) -> Result<WyrSubmission, Box<dyn Error + Send + Sync>>;
+
+  /**
+   * Get the submission identified by id, if there is one
+   */
+  fn get_submission(&self, id: i32) -> Result<Option<WyrSubmission>, Box<dyn Error + Send + Sync>>;
+
+  /**
+   * Approve or reject a pending submission. Return true if it was pending.
+   */
+  fn review_submission(
+    &self,
+    id: i32,
+    status: &str,
+    reviewed_by_id: u64,
+  ) -> Result<bool, Box<dyn Error + Send + Sync>>;
+
+  /**
+   * Get every approved submission in the guild
+   */
+  fn get_approved_submissions(
+    &self,
+    guild_id: u64,
+  ) -> Result<Vec<WyrSubmission>, Box<dyn Error + Send + Sync>>;
+
+  /**
+   * Get the ids of every wyr already shown in the guild, the most recently
+   * shown last
+   */
+  fn get_shown_wyr(&self, guild_id: u64) -> Result<Vec<String>, Box<dyn Error + Send + Sync>>;
+
+  /**
+   * Remember that a wyr was shown in the guild
+   */
+  fn mark_wyr_shown(&self, guild_id: u64, wyr_id: &str)
+    -> Result<(), Box<dyn Error + Send + Sync>>;
+
+  /**
+   * Forget that the wyrs were shown in the guild so they can come up again
+   */
+  fn reset_shown_wyr(
+    &self,
+    guild_id: u64,
+    wyr_ids: &[String],
+  ) -> Result<(), Box<dyn Error + Send + Sync>>;
+}
+
+pub trait ReactDb {
+  /**
+   * Save a new auto-reaction rule for a guild
+   */
+  fn save_react_rule(&self, rule: NewReactRule) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+  /**
+   * Get every auto-reaction rule in the guild, oldest first
+   */
+  fn get_react_rules(&self, guild_id: u64) -> Result<Vec<ReactRule>, Box<dyn Error + Send + Sync>>;
+
+  /**
+   * Get every auto-reaction rule in every guild
+   */
+  fn get_all_react_rules(&self) -> Result<Vec<ReactRule>, Box<dyn Error + Send + Sync>>;
+
+  /**
+   * Delete the guild's rule identified by id. Return true if it existed.
+   */
+  fn delete_react_rule(&self, guild_id: u64, id: i32)
+    -> Result<bool, Box<dyn Error + Send + Sync>>;
+}
+
+pub trait GameCacheDb {
+  /**
+   * Save a game, replacing whatever was cached under its id
+   */
+  fn cache_game(&self, game: NewCachedGame) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+  /**
+   * Get the cached game with the GiantBomb id, if there is one
+   */
+  fn get_cached_game(
+    &self,
+    game_id: i32,
+  ) -> Result<Option<CachedGame>, Box<dyn Error + Send + Sync>>;
+
+  /**
+   * Get the cached game at offset when ordered by id, if there is one
+   */
+  fn get_cached_game_at(
+    &self,
+    offset: i64,
+  ) -> Result<Option<CachedGame>, Box<dyn Error + Send + Sync>>;
+
+  /**
+   * Count every cached game
+   */
+  fn count_cached_games(&self) -> Result<i64, Box<dyn Error + Send + Sync>>;
 }
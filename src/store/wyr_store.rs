@@ -0,0 +1,408 @@
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use tracing::{error, info, warn};
+
+// a poll is a single discord action row, which holds at most 5 buttons
+const MIN_OPTIONS: usize = 2;
+const MAX_OPTIONS: usize = 5;
+// discord rejects button labels longer than this
+const MAX_LABEL_LEN: usize = 80;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Wyr {
+  pub id: String,
+  pub title: String,
+  pub url: String,
+  pub options: Vec<String>,
+  // only set for polls members submitted through /wyr-submit
+  #[serde(default)]
+  pub submitted_by: Option<u64>,
+}
+
+/**
+ * Lines that didn't make it into the store. Malformed lines aren't wyr
+ * json at all, skipped lines parsed fine but can't be shown as a poll.
+ */
+#[derive(Default, Debug)]
+pub struct LoadReport {
+  pub malformed: Vec<String>,
+  pub skipped: Vec<String>,
+}
+
+/**
+ * What changed when the store was reloaded, and the lines left out like
+ * they are at startup
+ */
+#[derive(Debug)]
+pub struct ReloadSummary {
+  pub added: Vec<String>,
+  pub removed: Vec<String>,
+  pub skipped: Vec<String>,
+  pub total: usize,
+}
+
+/**
+ * Every wyr collection in db/wyr, parsed once and kept in memory.
+ * Collections are named after their file: wyr-<name>.ndjson
+ */
+#[derive(Default, Debug)]
+struct WyrData {
+  collections: HashMap<String, Vec<Arc<Wyr>>>,
+  by_id: HashMap<String, Arc<Wyr>>,
+}
+
+impl WyrData {
+  /**
+   * Load every collection in dir. Only failing to read the directory or a
+   * file is an error; bad lines are left out and listed in the report.
+   */
+  fn load(dir: &Path) -> Result<(Self, LoadReport), Box<dyn Error + Send + Sync>> {
+    let mut data = WyrData::default();
+    let mut report = LoadReport::default();
+
+    for entry in fs::read_dir(dir)? {
+      let path = entry?.path();
+      let name = match collection_name(&path) {
+        Some(n) => n,
+        None => continue,
+      };
+
+      let contents = fs::read_to_string(&path)?;
+      let mut wyrs = Vec::<Arc<Wyr>>::new();
+      for (idx, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+          continue;
+        }
+
+        let location = format!("{}:{}", path.display(), idx + 1);
+        let wyr = match serde_json::from_str::<Wyr>(line) {
+          Ok(w) => w,
+          Err(why) => {
+            report.malformed.push(format!("{}: {}", location, why));
+            continue;
+          }
+        };
+
+        if let Err(why) = validate(&wyr) {
+          report
+            .skipped
+            .push(format!("{}: {} ({})", location, why, wyr.id));
+          continue;
+        }
+
+        if wyrs.iter().any(|w| w.id == wyr.id) {
+          report
+            .skipped
+            .push(format!("{}: duplicate id ({})", location, wyr.id));
+          continue;
+        }
+
+        let wyr = Arc::new(wyr);
+        data
+          .by_id
+          .entry(wyr.id.clone())
+          .or_insert_with(|| Arc::clone(&wyr));
+        wyrs.push(wyr);
+      }
+
+      data.collections.insert(name, wyrs);
+    }
+
+    Ok((data, report))
+  }
+
+  fn log(&self, report: &LoadReport) {
+    for line in report.malformed.iter() {
+      error!("Malformed wyr {}", line);
+    }
+    for line in report.skipped.iter() {
+      warn!("Skipped wyr {}", line);
+    }
+    for (name, wyrs) in self.collections.iter() {
+      info!("Loaded {} wyr from collection {}", wyrs.len(), name);
+    }
+  }
+}
+
+/**
+ * Shared handle on the wyr collections. Readers grab a snapshot of the
+ * current data, so a reload swaps everything at once and never leaves
+ * anyone looking at half a dataset.
+ */
+#[derive(Debug)]
+pub struct WyrStore {
+  dir: PathBuf,
+  data: RwLock<Arc<WyrData>>,
+}
+
+impl WyrStore {
+  /**
+   * Load the store and log how it went, falling back to an empty store
+   * when the directory can't be read so the rest of the bot still works
+   */
+  pub fn load_or_empty(dir: &Path) -> Self {
+    let data = match WyrData::load(dir) {
+      Ok((data, report)) => {
+        data.log(&report);
+        data
+      }
+      Err(why) => {
+        error!("Failed to load wyr from {}: {}", dir.display(), why);
+        WyrData::default()
+      }
+    };
+
+    Self {
+      dir: dir.to_path_buf(),
+      data: RwLock::new(Arc::new(data)),
+    }
+  }
+
+  /**
+   * Re-read the directory and swap in the new data. If any file can't be
+   * read or the new data fails `check_reload`, the old data stays.
+   */
+  pub fn reload(&self) -> Result<ReloadSummary, Box<dyn Error + Send + Sync>> {
+    let (data, report) = WyrData::load(&self.dir)?;
+    let old = self.snapshot();
+    check_reload(&old, &data, &report).map_err(|why| format!("{}, keeping the old data", why))?;
+
+    let (added, removed) = diff_ids(
+      old.by_id.keys().map(String::as_str),
+      data.by_id.keys().map(String::as_str),
+    );
+    let total = data.by_id.len();
+    data.log(&report);
+
+    *self.data.write().expect("wyr store lock poisoned") = Arc::new(data);
+    info!(
+      "Reloaded wyr: {} total, {} added {:?}, {} removed {:?}",
+      total,
+      added.len(),
+      added,
+      removed.len(),
+      removed
+    );
+
+    Ok(ReloadSummary {
+      added,
+      removed,
+      skipped: report.skipped,
+      total,
+    })
+  }
+
+  /**
+   * Every wyr in the collection, or in every collection when the name
+   * isn't one we know
+   */
+  pub fn pool(&self, collection: &str) -> Vec<Arc<Wyr>> {
+    let data = self.snapshot();
+    match data.collections.get(collection) {
+      Some(wyrs) => wyrs.to_vec(),
+      None => data.collections.values().flatten().cloned().collect(),
+    }
+  }
+
+  /**
+   * Find a wyr by its id in any collection
+   */
+  pub fn get(&self, id: &str) -> Option<Arc<Wyr>> {
+    self.snapshot().by_id.get(id).map(Arc::clone)
+  }
+
+  fn snapshot(&self) -> Arc<WyrData> {
+    Arc::clone(&self.data.read().expect("wyr store lock poisoned"))
+  }
+}
+
+/**
+ * Whether freshly loaded data is fit to replace the old. Every line has to
+ * be wyr json, and every collection there was before still has to have
+ * polls. Lines that can't be shown as a poll are skipped, same as startup.
+ */
+fn check_reload(old: &WyrData, new: &WyrData, report: &LoadReport) -> Result<(), String> {
+  if let Some(first) = report.malformed.first() {
+    return Err(format!(
+      "{} malformed line(s). First: {}",
+      report.malformed.len(),
+      first
+    ));
+  }
+  if new.by_id.is_empty() {
+    return Err(String::from("No wyr found"));
+  }
+
+  let mut emptied = old
+    .collections
+    .iter()
+    .filter(|(_, wyrs)| !wyrs.is_empty())
+    .map(|(name, _)| name)
+    .filter(|name| new.collections.get(*name).map_or(0, Vec::len) == 0)
+    .cloned()
+    .collect::<Vec<String>>();
+  if !emptied.is_empty() {
+    emptied.sort();
+    return Err(format!(
+      "Collection(s) {} would be missing or empty",
+      emptied.join(", ")
+    ));
+  }
+
+  Ok(())
+}
+
+/**
+ * Ids only in `new` are added, ids only in `old` are removed. Both sorted.
+ */
+fn diff_ids<'a>(
+  old: impl Iterator<Item = &'a str>,
+  new: impl Iterator<Item = &'a str>,
+) -> (Vec<String>, Vec<String>) {
+  let old = old.collect::<HashSet<&str>>();
+  let new = new.collect::<HashSet<&str>>();
+  let mut added = new
+    .difference(&old)
+    .map(|id| String::from(*id))
+    .collect::<Vec<String>>();
+  let mut removed = old
+    .difference(&new)
+    .map(|id| String::from(*id))
+    .collect::<Vec<String>>();
+  added.sort();
+  removed.sort();
+  (added, removed)
+}
+
+fn collection_name(path: &Path) -> Option<String> {
+  let file = path.file_name()?.to_str()?;
+  let name = file.strip_prefix("wyr-")?.strip_suffix(".ndjson")?;
+  Some(String::from(name))
+}
+
+/**
+ * Check the wyr can actually be posted as a poll
+ */
+pub fn validate(wyr: &Wyr) -> Result<(), String> {
+  if wyr.id.trim().is_empty() {
+    return Err(String::from("missing id"));
+  }
+  if wyr.title.trim().is_empty() {
+    return Err(String::from("missing title"));
+  }
+  if wyr.options.len() < MIN_OPTIONS || wyr.options.len() > MAX_OPTIONS {
+    return Err(format!(
+      "has {} options, polls need {} to {}",
+      wyr.options.len(),
+      MIN_OPTIONS,
+      MAX_OPTIONS
+    ));
+  }
+  if let Some(opt) = wyr
+    .options
+    .iter()
+    .find(|o| o.trim().is_empty() || o.chars().count() > MAX_LABEL_LEN)
+  {
+    return Err(format!(
+      "option '{}' must be 1 to {} characters",
+      opt, MAX_LABEL_LEN
+    ));
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn wyr(options: &[&str]) -> Wyr {
+    Wyr {
+      id: String::from("1"),
+      title: String::from("WYR?"),
+      url: String::from("https://reddit.com"),
+      options: options.iter().map(|o| String::from(*o)).collect(),
+      submitted_by: None,
+    }
+  }
+
+  #[test]
+  fn validate_accepts_two_to_five_options() {
+    assert!(validate(&wyr(&["a", "b"])).is_ok());
+    assert!(validate(&wyr(&["a", "b", "c", "d", "e"])).is_ok());
+  }
+
+  #[test]
+  fn validate_rejects_polls_that_dont_fit_an_action_row() {
+    assert!(validate(&wyr(&["a"])).is_err());
+    assert!(validate(&wyr(&["a", "b", "c", "d", "e", "f"])).is_err());
+    assert!(validate(&wyr(&["a", &"b".repeat(81)])).is_err());
+    assert!(validate(&wyr(&["a", " "])).is_err());
+  }
+
+  #[test]
+  fn collection_name_comes_from_file_name() {
+    assert_eq!(
+      collection_name(Path::new("db/wyr/wyr-top-all.ndjson")),
+      Some(String::from("top-all"))
+    );
+    assert_eq!(collection_name(Path::new("db/wyr/notes.txt")), None);
+  }
+
+  fn data(collections: &[(&str, &[&str])]) -> WyrData {
+    let mut data = WyrData::default();
+    for (name, ids) in collections.iter() {
+      let wyrs = ids
+        .iter()
+        .map(|id| {
+          let mut w = wyr(&["a", "b"]);
+          w.id = String::from(*id);
+          Arc::new(w)
+        })
+        .collect::<Vec<Arc<Wyr>>>();
+      for w in wyrs.iter() {
+        data.by_id.insert(w.id.clone(), Arc::clone(w));
+      }
+      data.collections.insert(String::from(*name), wyrs);
+    }
+    data
+  }
+
+  #[test]
+  fn check_reload_keeps_old_data_on_bad_files_or_emptied_collections() {
+    let old = data(&[("hot", &["1", "2"]), ("new", &["3"])]);
+    let ok = LoadReport::default();
+
+    assert!(check_reload(&old, &data(&[("hot", &["1"]), ("new", &["4"])]), &ok).is_ok());
+    assert!(check_reload(&old, &data(&[("hot", &["1"])]), &ok).is_err());
+    assert!(check_reload(&old, &data(&[("hot", &["1"]), ("new", &[])]), &ok).is_err());
+    assert!(check_reload(&old, &data(&[]), &ok).is_err());
+
+    let new = data(&[("hot", &["1"]), ("new", &["3"])]);
+    let skipped = LoadReport {
+      malformed: vec![],
+      skipped: vec![String::from("wyr-hot.ndjson:3: missing title (5)")],
+    };
+    assert!(check_reload(&old, &new, &skipped).is_ok());
+
+    let malformed = LoadReport {
+      malformed: vec![String::from("wyr-hot.ndjson:4: expected value")],
+      skipped: vec![],
+    };
+    assert!(check_reload(&old, &new, &malformed).is_err());
+  }
+
+  #[test]
+  fn diff_ids_finds_added_and_removed() {
+    let (added, removed) = diff_ids(
+      vec!["a", "b", "c"].into_iter(),
+      vec!["b", "d", "c", "e"].into_iter(),
+    );
+    assert_eq!(added, vec!["d", "e"]);
+    assert_eq!(removed, vec!["a"]);
+  }
+}